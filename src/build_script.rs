@@ -0,0 +1,805 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::install::{
+    check_git_dir_readable, common_git_dir, discover_git_dir, emit_metadata, env_flag,
+    install_hooks_with, is_managed_hook, normalize_path, timed, warn, Config, HookTarget,
+    HuskyError, LogLevel, ResolvedTarget, Result, Session, Settings, CI_ENV_VARS, HUSKY_DIR,
+    HUSKY_HOOKS_DIR, HUSKY_MARKER, STAGED_HOOKS_DIR,
+};
+
+// The build script's entry point, which the library's tests build but never call
+#[cfg_attr(test, allow(dead_code))]
+pub(crate) fn run_build_script() {
+    let session = Session::new(true, env::var_os("OUT_DIR").map(PathBuf::from));
+    let (result, session) = session.run(build_script_result);
+    let (_, timings) = session.finish();
+    if env_flag("HUSKY_TIMING") {
+        report_timings(&timings);
+    }
+    if let Err(error) = result {
+        std::process::exit(error.exit_code());
+    }
+}
+
+// Everything the build script does, short of exiting on an error it already reported
+fn build_script_result() -> Result<()> {
+    for var in WATCHED_ENV_VARS {
+        cargo!("cargo:rerun-if-env-changed={}", var);
+    }
+
+    if hooks_disabled() {
+        log!(
+            LogLevel::Info,
+            "NO_HUSKY_HOOKS is set, skipping hook installation"
+        );
+        return Ok(());
+    }
+
+    if env_flag("HUSKY_SELFTEST") {
+        self_test().inspect_err(|error| log!(LogLevel::Error, "{}", error))?;
+        log!(
+            LogLevel::Info,
+            "Self-test passed, hooks install and run here"
+        );
+        return Ok(());
+    }
+
+    // Manifest settings are only known once the project is found, so whether a missing
+    // project is fatal can only come from the environment
+    let strict = env_flag("HUSKY_STRICT");
+    // Exporting takes precedence, it never means to install anything for git
+    let dest_dir = ["HUSKY_EXPORT_DIR", "HUSKY_DEST_DIR"]
+        .into_iter()
+        .find_map(|var| {
+            let dir = env::var_os(var).filter(|dir| !dir.is_empty())?;
+            Some(DestDirTarget(PathBuf::from(dir), var))
+        });
+    let result = match dest_dir {
+        Some(dest_dir) => install_hooks(&dest_dir),
+        None => install_hooks_in_git_dir(),
+    };
+    match result {
+        // Building outside a git repository is expected (e.g. from a crate tarball, or a
+        // submodule copied without its superproject), unless strict mode demands hooks
+        Err(error @ (HuskyError::GitDirNotFound(_) | HuskyError::PathResolution(..)))
+            if !strict =>
+        {
+            log!(LogLevel::Info, "{}, skipping hook installation", error);
+            Ok(())
+        }
+        Err(error) => {
+            log!(LogLevel::Error, "Error during hook installation: {}", error);
+            Err(error)
+        }
+        Ok(()) => Ok(()),
+    }
+}
+
+// Time spent per phase, where a phase that never ran is reported as taking no time
+const TIMED_PHASES: [&str; 3] = ["git dir discovery", "reading", "writing"];
+
+// Shown as warnings, the only build script output cargo prints without `-vv`
+fn report_timings(timings: &[(&str, Duration)]) {
+    for phase in TIMED_PHASES {
+        let elapsed = timings
+            .iter()
+            .find(|(name, _)| *name == phase)
+            .map_or(Duration::ZERO, |(_, elapsed)| *elapsed);
+        println!(
+            "cargo:warning=husky-rs timing: {} took {:?}",
+            phase, elapsed
+        );
+    }
+}
+
+// Environment variables that influence installation, so cargo reruns us when they change
+const WATCHED_ENV_VARS: &[&str] = &[
+    "NO_HUSKY_HOOKS",
+    "HUSKY_FORCE",
+    "HUSKY_LOG",
+    "HUSKY_STRICT",
+    "HUSKY_ONLY_MISSING",
+    "HUSKY_SERVER_HOOKS",
+    "HUSKY_STRICT_BODY",
+    "HUSKY_HOOKS_SRC",
+    "HUSKY_SELF_INSTALL",
+    "HUSKY_HOOKS_ARCHIVE",
+    "HUSKY_CHECK_INTERPRETERS",
+    "HUSKY_CHECK_SYNTAX",
+    "HUSKY_CHECK_IGNORED",
+    "HUSKY_CHECK",
+    "HUSKY_CLEAN",
+    "HUSKY_LIST",
+    "HUSKY_STAGE_WITHOUT_GIT",
+    "HUSKY_REVIEW",
+    "HUSKY_PROMOTE",
+    "HUSKY_TIMING",
+    "HUSKY_SELFTEST",
+    "HUSKY_RAW",
+    "HUSKY_REQUIRED_HOOKS",
+    "HUSKY_ON_ERROR",
+    "HUSKY_CONTINUE_ON_ERROR",
+    "HUSKY_ABORT_ON_ERROR",
+    "CARGO_WORKSPACE_DIR",
+    "HUSKY_GENERATORS",
+    "HUSKY_HEADER_VERSION",
+    "HUSKY_FOOTER",
+    "HUSKY_FOOTER_ON_EXIT",
+    "HUSKY_SOURCE_COMMENT",
+    "HUSKY_WRITE_RETRIES",
+    "HUSKY_TICKET_PATTERN",
+    "HUSKY_HEADER_STYLE",
+    "HUSKY_HEADER_WIDTH",
+    "HUSKY_HEADER_TIMESTAMP",
+    "HUSKY_HUSKY_DIR_HOOKS",
+    "HUSKY_EXEC_PAYLOAD",
+    "HUSKY_PRESERVE_COMMENTS",
+    "HUSKY_FMT_CHECK",
+    "HUSKY_FMT_ARGS",
+    "HUSKY_DEFAULT_SHEBANG",
+    "HUSKY_REQUIRE_SHEBANG",
+    "HUSKY_CANONICAL_SHEBANG",
+    "HUSKY_FORCE_CANONICAL_SHEBANG",
+    "HUSKY_REPORT",
+    "HUSKY_PROFILES",
+    "HUSKY_SKIP_IN_CI",
+    "HUSKY_DEV_HOOKS",
+    "HUSKY_EXTRA_HOOKS",
+    "HUSKY_OPAQUE_HOOKS",
+    "HUSKY_MAX_HOOK_SIZE",
+    "HUSKY_PROTECTED_REFS",
+    "HUSKY_HOOK_MODE",
+    "HUSKY_GIT_BASH",
+    "HUSKY_OWNER",
+    "HUSKY_EMPTY_HOOK",
+    "HUSKY_DEST_DIR",
+    "HUSKY_EXPORT_DIR",
+    "GIT_COMMON_DIR",
+    "GIT_DIR",
+    "GIT_WORK_TREE",
+];
+
+// Disabling wins over forcing: `NO_HUSKY_HOOKS` is the switch people reach for to make sure
+// nothing gets touched, so `HUSKY_FORCE` must not undo it
+fn hooks_disabled() -> bool {
+    let disabled = env::var_os("NO_HUSKY_HOOKS").is_some();
+    if disabled && env_flag("HUSKY_FORCE") {
+        warn("Both NO_HUSKY_HOOKS and HUSKY_FORCE are set, NO_HUSKY_HOOKS takes precedence and no hooks are installed");
+    }
+    disabled
+}
+
+impl HuskyError {
+    // Documented in the README. cargo exits with 101 regardless and only reports this status in
+    // its error output, which is where scripts around `cargo build` can tell failures apart
+    fn exit_code(&self) -> i32 {
+        match self {
+            HuskyError::GitDirNotFound(_) | HuskyError::PathResolution(..) => 2,
+            HuskyError::GitDirUnreadable(..) | HuskyError::HookNotReadable(_) => 3,
+            HuskyError::Io(err) if err.kind() == io::ErrorKind::PermissionDenied => 3,
+            HuskyError::Config(_) | HuskyError::Env(_) => 4,
+            HuskyError::OutOfSync(_) => 5,
+            HuskyError::Io(_)
+            | HuskyError::EmptyUserHook(_)
+            | HuskyError::AmbiguousHook(..)
+            | HuskyError::DiskFull(_)
+            | HuskyError::Generator(..)
+            | HuskyError::MissingShebang(_)
+            | HuskyError::SyntaxError(..)
+            | HuskyError::SelfTest(_)
+            | HuskyError::MissingRequiredHooks(..)
+            | HuskyError::HookTooLarge(..)
+            | HuskyError::InvalidHooks(_) => 1,
+        }
+    }
+}
+
+fn install_hooks_in_git_dir() -> Result<()> {
+    match install_hooks(&GitTarget) {
+        Err(HuskyError::GitDirNotFound(path)) => {
+            install_hooks(&StagedTarget).map_err(|error| match error {
+                // Report the repository that wasn't found, not the missing opt-in
+                HuskyError::GitDirNotFound(_) => HuskyError::GitDirNotFound(path),
+                error => error,
+            })
+        }
+        result => result,
+    }
+}
+
+fn install_hooks(target: &impl HookTarget) -> Result<()> {
+    let resolved = timed("git dir discovery", || target.resolve())?;
+    let manifest = resolved.project_root.join("Cargo.toml");
+    let config = Config::load(&manifest)?;
+    if BuildSettings::load(&manifest)?.holds_back(&resolved.project_root) {
+        return Ok(());
+    }
+    install_hooks_with(resolved, config)
+}
+
+// Settings only the build script acts on, as only it knows the build's profile, whether it runs
+// in CI, and whether husky-rs is building itself
+struct BuildSettings {
+    // Install hooks even when husky-rs itself is the project being built
+    self_install: bool,
+    // Install even where husky-rs would otherwise hold back, like its own build
+    force: bool,
+    // Cargo profiles (as in `PROFILE`, e.g. `debug`) hooks are installed for, all when empty
+    profiles: Vec<String>,
+    // Install nothing when a CI provider is detected from its environment variables
+    skip_in_ci: bool,
+    // Stage hooks in `.husky/.staged-hooks` when there is no git directory to install into
+    stage_without_git: bool,
+}
+
+impl BuildSettings {
+    fn load(manifest: &Path) -> Result<Self> {
+        let settings = Settings::load(manifest)?;
+        Ok(BuildSettings {
+            self_install: settings.flag("self_install")?,
+            force: env_flag("HUSKY_FORCE"),
+            profiles: settings.array("profiles")?.unwrap_or_default(),
+            skip_in_ci: settings.flag("skip_in_ci")?,
+            stage_without_git: settings.flag("stage_without_git")?,
+        })
+    }
+
+    // Whether this build installs nothing, saying why
+    fn holds_back(&self, project_root: &Path) -> bool {
+        if !self.self_install
+            && !self.force
+            && is_self_build(Path::new(env!("CARGO_MANIFEST_DIR")), project_root)
+        {
+            log!(
+                LogLevel::Info,
+                "Building {} itself, skipping hook installation (set `self_install = true` to install)",
+                env!("CARGO_PKG_NAME")
+            );
+            return true;
+        }
+        if let Ok(profile) = env::var("PROFILE") {
+            emit_metadata("profile", &profile);
+            if !self.profiles.is_empty() && !self.profiles.contains(&profile) && !self.force {
+                log!(
+                    LogLevel::Info,
+                    "Skipping hook installation for the '{}' profile, `profiles` only lists {}",
+                    profile,
+                    self.profiles.join(", ")
+                );
+                return true;
+            }
+        }
+        if self.skip_in_ci && !self.force {
+            for var in CI_ENV_VARS {
+                cargo!("cargo:rerun-if-env-changed={}", var);
+            }
+            if let Some(var) = CI_ENV_VARS.iter().find(|var| env_flag(var)) {
+                log!(
+                    LogLevel::Info,
+                    "{} is set, skipping hook installation in CI (`skip_in_ci = true`)",
+                    var
+                );
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// Developing husky-rs builds this script with the husky-rs repository as the project, whereas
+// a dependent's build runs it from the registry, a git checkout or a path outside the project
+fn is_self_build(manifest_dir: &Path, project_root: &Path) -> bool {
+    match (
+        fs::canonicalize(manifest_dir),
+        fs::canonicalize(project_root),
+    ) {
+        (Ok(manifest_dir), Ok(project_root)) => manifest_dir == project_root,
+        _ => false,
+    }
+}
+
+struct GitTarget;
+
+impl HookTarget for GitTarget {
+    fn resolve(&self) -> Result<ResolvedTarget> {
+        let start_dir = start_dir()?;
+        let (git_dir, work_tree) = find_git_dir(&start_dir)?;
+        // With a split layout, `.husky` lives in the work tree rather than next to the git dir
+        let project_root = match work_tree_override(env::var_os("GIT_WORK_TREE")).or(work_tree) {
+            Some(work_tree) => work_tree,
+            None => find_project_root(&git_dir)?,
+        };
+        // A linked worktree runs the hooks of the common dir it shares with the main worktree
+        let git_dir = match git_dir_from_env("GIT_COMMON_DIR", &start_dir) {
+            Some(common_dir) => common_dir,
+            None => common_git_dir(git_dir)?,
+        };
+        check_git_dir_readable(&git_dir)?;
+        Ok(ResolvedTarget {
+            project_root,
+            hooks_dir: git_dir.join("hooks"),
+            repo_dir: git_dir,
+        })
+    }
+}
+
+// Without a git directory, e.g. in a `git archive` export, hooks can be staged next to their
+// sources for copying into `.git/hooks` after a later `git init`. The project is found by its
+// `.husky` directory, and staging must be enabled with `stage_without_git`.
+struct StagedTarget;
+
+impl HookTarget for StagedTarget {
+    fn resolve(&self) -> Result<ResolvedTarget> {
+        let start_dir = start_dir()?;
+        let not_found = || HuskyError::GitDirNotFound(start_dir.display().to_string());
+        let project_root = start_dir
+            .ancestors()
+            .find(|dir| dir.join(HUSKY_DIR).is_dir())
+            .ok_or_else(not_found)?
+            .to_path_buf();
+        let manifest = project_root.join("Cargo.toml");
+        if manifest.is_file() {
+            // So enabling `stage_without_git` later takes effect on the next build
+            cargo!("cargo:rerun-if-changed={}", manifest.display());
+        }
+        if !BuildSettings::load(&manifest)?.stage_without_git {
+            return Err(not_found());
+        }
+        let staged = project_root.join(HUSKY_DIR).join(STAGED_HOOKS_DIR);
+        log!(
+            LogLevel::Info,
+            "No git directory found, staging hooks in '{}'",
+            staged.display()
+        );
+        Ok(ResolvedTarget {
+            repo_dir: staged.clone(),
+            project_root,
+            hooks_dir: staged,
+        })
+    }
+}
+
+// `HUSKY_DEST_DIR` names the hooks directory outright, e.g. in a container or a test, so no
+// git directory is looked for, and so does `HUSKY_EXPORT_DIR`. The project is the closest one
+// around the build's `OUT_DIR`, and a relative destination is taken from its root.
+struct DestDirTarget(PathBuf, &'static str);
+
+impl HookTarget for DestDirTarget {
+    fn resolve(&self) -> Result<ResolvedTarget> {
+        let start_dir = start_dir()?;
+        let project_root = start_dir
+            .ancestors()
+            .find(|dir| dir.join("Cargo.toml").is_file() || dir.join(HUSKY_DIR).is_dir())
+            .ok_or_else(|| {
+                HuskyError::Config(format!(
+                    "`{}` is set, but no project was found around '{}'",
+                    self.1,
+                    start_dir.display()
+                ))
+            })?
+            .to_path_buf();
+        let dest_dir = normalize_path(&project_root.join(&self.0));
+        if !dest_dir.is_dir() {
+            return Err(HuskyError::Config(format!(
+                "`{}` '{}' is not an existing directory",
+                self.1,
+                dest_dir.display()
+            )));
+        }
+        log!(
+            LogLevel::Debug,
+            "Installing hooks into '{}' from `{}`",
+            dest_dir.display(),
+            self.1
+        );
+        Ok(ResolvedTarget {
+            repo_dir: dest_dir.clone(),
+            project_root,
+            hooks_dir: dest_dir,
+        })
+    }
+}
+
+// A throwaway repository of the self-test, created under the build directory
+struct SelfTestTarget(PathBuf);
+
+impl HookTarget for SelfTestTarget {
+    fn resolve(&self) -> Result<ResolvedTarget> {
+        let git_dir = self.0.join(".git");
+        Ok(ResolvedTarget {
+            hooks_dir: git_dir.join("hooks"),
+            repo_dir: git_dir,
+            project_root: self.0.clone(),
+        })
+    }
+}
+
+// Installs a sample hook into a fresh repository and runs it, the way the integration tests
+// do, so platform or permission problems show without the user's own project in the way
+fn self_test() -> Result<()> {
+    const SELF_TEST_OUTPUT: &str = "husky-rs self-test hook ran";
+    let repo = start_dir()?.join("husky-selftest");
+    if repo.exists() {
+        fs::remove_dir_all(&repo)?;
+    }
+    let user_hooks_dir = repo.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR);
+    fs::create_dir_all(&user_hooks_dir)?;
+    let init = std::process::Command::new("git")
+        .args(["init", "--quiet"])
+        .current_dir(&repo)
+        .output()
+        .map_err(|err| HuskyError::SelfTest(format!("cannot run `git init`: {}", err)))?;
+    if !init.status.success() {
+        return Err(HuskyError::SelfTest(format!(
+            "`git init` failed: {}",
+            String::from_utf8_lossy(&init.stderr).trim()
+        )));
+    }
+    fs::write(
+        user_hooks_dir.join("pre-commit"),
+        format!("#!/bin/sh\necho \"{}\"\n", SELF_TEST_OUTPUT),
+    )?;
+    // Neither the project's settings nor the environment get a say, the sample hook is always
+    // installed the same way
+    install_hooks_with(SelfTestTarget(repo.clone()).resolve()?, Config::default())?;
+
+    let hook = repo.join(".git").join("hooks").join("pre-commit");
+    let content = fs::read_to_string(&hook).map_err(|err| {
+        HuskyError::SelfTest(format!("'{}' was not installed: {}", hook.display(), err))
+    })?;
+    if !is_managed_hook(&content) {
+        return Err(HuskyError::SelfTest(format!(
+            "'{}' lacks the `{}` marker",
+            hook.display(),
+            HUSKY_MARKER
+        )));
+    }
+    let run = std::process::Command::new(&hook)
+        .current_dir(&repo)
+        .output()
+        .map_err(|err| {
+            HuskyError::SelfTest(format!("'{}' cannot be run: {}", hook.display(), err))
+        })?;
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    if !run.status.success() || !stdout.contains(SELF_TEST_OUTPUT) {
+        return Err(HuskyError::SelfTest(format!(
+            "'{}' did not run as expected ({}): {}",
+            hook.display(),
+            run.status,
+            String::from_utf8_lossy(&run.stderr).trim()
+        )));
+    }
+    fs::remove_dir_all(&repo)?;
+    Ok(())
+}
+
+// Where looking for the project starts: `OUT_DIR` in a build script, which for a dependency of
+// the project is inside its target directory, and the current directory for any other caller
+fn start_dir() -> Result<PathBuf> {
+    discovery_start(env::var_os("OUT_DIR"))
+}
+
+fn discovery_start(out_dir: Option<std::ffi::OsString>) -> Result<PathBuf> {
+    match out_dir {
+        Some(out_dir) => Ok(PathBuf::from(out_dir)),
+        // E.g. the directory was deleted from under the process
+        None => env::current_dir().map_err(|err| {
+            HuskyError::PathResolution(PathBuf::from("."), "current directory".to_string(), err)
+        }),
+    }
+}
+
+// Finds the git dir of the work tree being built, from GIT_DIR or by walking up from where
+// discovery starts, along with the work tree when discovery found it
+fn find_git_dir(start_dir: &Path) -> Result<(PathBuf, Option<PathBuf>)> {
+    if let Some(git_dir) = env::var_os("GIT_DIR").map(PathBuf::from) {
+        if git_dir.is_absolute() && !git_dir.is_dir() {
+            return Err(HuskyError::GitDirNotFound(git_dir.display().to_string()));
+        }
+    }
+    if let Some(git_dir) = git_dir_from_env("GIT_DIR", start_dir) {
+        return Ok((git_dir, None));
+    }
+
+    let (git_dir, work_tree) = discover_git_dir(start_dir)?
+        .ok_or_else(|| HuskyError::GitDirNotFound(start_dir.display().to_string()))?;
+    Ok((git_dir, Some(work_tree)))
+}
+
+// Resolves a git directory named by `var`, absolute or relative to a directory above
+// `start_dir`, or None when it is unset or names no directory
+fn git_dir_from_env(var: &str, start_dir: &Path) -> Option<PathBuf> {
+    let git_dir = PathBuf::from(env::var_os(var)?);
+    let resolved = if git_dir.is_absolute() {
+        Some(git_dir.clone()).filter(|dir| dir.is_dir())
+    } else {
+        resolve_relative_git_dir(&git_dir, start_dir)
+    };
+    if resolved.is_none() {
+        log!(
+            LogLevel::Debug,
+            "Ignoring {} '{}', which names no directory above '{}'",
+            var,
+            git_dir.display(),
+            start_dir.display()
+        );
+    }
+    resolved
+}
+
+// Git exports e.g. `GIT_DIR=.git` to hooks, relative to the directory the hook runs in, while a
+// build script runs from its own package directory, so look for it above where discovery starts
+fn resolve_relative_git_dir(git_dir: &Path, start_dir: &Path) -> Option<PathBuf> {
+    start_dir
+        .ancestors()
+        .map(|dir| dir.join(git_dir))
+        .find(|candidate| candidate.is_dir())
+}
+
+// The work tree of a git dir named without one, e.g. by GIT_DIR
+fn find_project_root(git_dir: &Path) -> Result<PathBuf> {
+    // A linked worktree's git dir lives under the main one's and names its `.git` file
+    if let Ok(git_file) = fs::read_to_string(git_dir.join("gitdir")) {
+        if let Some(work_tree) = Path::new(git_file.trim()).parent() {
+            return Ok(work_tree.to_path_buf());
+        }
+    }
+
+    // `/.git` and `C:\.git` have the root as their parent, which is a fine project root, but
+    // a bare `.git` has an empty one that would make every path relative to nothing
+    match git_dir.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Ok(PathBuf::from(".")),
+        Some(parent) => Ok(parent.to_path_buf()),
+        None => Err(HuskyError::GitDirNotFound(git_dir.display().to_string())),
+    }
+}
+
+// A relative work tree is relative to wherever git was invoked, which a build script running
+// from its own package directory cannot know, so only an absolute one is trusted
+fn work_tree_override(work_tree: Option<std::ffi::OsString>) -> Option<PathBuf> {
+    let work_tree = PathBuf::from(work_tree?);
+    if work_tree.is_absolute() {
+        return Some(work_tree);
+    }
+    warn(&format!(
+        "Ignoring relative GIT_WORK_TREE '{}', set it to an absolute path",
+        work_tree.display()
+    ));
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install::tests::{git, temp_dir, temp_repo, TEST_HOOK};
+    use crate::install::HUSKY_HEADER;
+
+    struct MockTarget(PathBuf);
+
+    impl HookTarget for MockTarget {
+        fn resolve(&self) -> Result<ResolvedTarget> {
+            Ok(ResolvedTarget {
+                repo_dir: self.0.join(".mock"),
+                project_root: self.0.clone(),
+                hooks_dir: self.0.join(".mock").join("hook-scripts"),
+            })
+        }
+    }
+
+    #[test]
+    fn test_install_hooks_uses_target() {
+        let dir = temp_dir("mock-target");
+        let hooks_src = dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR);
+        fs::create_dir_all(&hooks_src).unwrap();
+        fs::write(hooks_src.join("pre-commit"), "#!/bin/sh\necho mock\n").unwrap();
+
+        install_hooks(&MockTarget(dir.clone())).unwrap();
+        let installed =
+            fs::read_to_string(dir.join(".mock").join("hook-scripts").join("pre-commit")).unwrap();
+        assert!(installed.contains(HUSKY_HEADER), "{}", installed);
+        assert!(installed.contains("echo mock"), "{}", installed);
+        assert!(!dir.join(".git").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_hooks_reports_every_empty_hook() {
+        let dir = temp_dir("every-empty-hook");
+        let hooks_src = dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR);
+        fs::create_dir_all(&hooks_src).unwrap();
+        fs::write(hooks_src.join("commit-msg"), "#!/bin/sh\necho valid\n").unwrap();
+        fs::write(hooks_src.join("pre-commit"), "\n").unwrap();
+        fs::write(hooks_src.join("pre-push"), "#!/bin/sh\n").unwrap();
+
+        let error = install_hooks(&MockTarget(dir.clone())).unwrap_err();
+        assert!(
+            matches!(&error, HuskyError::InvalidHooks(errors) if errors.len() == 2),
+            "{:?}",
+            error
+        );
+        let message = error.to_string();
+        for hook in ["pre-commit", "pre-push"] {
+            assert!(
+                message.contains(&hooks_src.join(hook).display().to_string()),
+                "{}",
+                message
+            );
+        }
+        assert!(dir
+            .join(".mock")
+            .join("hook-scripts")
+            .join("commit-msg")
+            .exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_project_root_at_filesystem_root() {
+        for git_dir in ["/.git", "//.git"] {
+            let root = find_project_root(Path::new(git_dir)).unwrap();
+            assert_eq!(root, Path::new("/"));
+            assert_eq!(
+                root.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR),
+                Path::new("/.husky/hooks")
+            );
+        }
+        assert_eq!(
+            find_project_root(Path::new(".git")).unwrap(),
+            Path::new(".")
+        );
+        assert!(matches!(
+            find_project_root(Path::new("/")),
+            Err(HuskyError::GitDirNotFound(_))
+        ));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_find_project_root_at_drive_root() {
+        let root = find_project_root(Path::new(r"C:\.git")).unwrap();
+        assert_eq!(root, Path::new(r"C:\"));
+        assert_eq!(
+            root.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR),
+            Path::new(r"C:\.husky\hooks")
+        );
+        assert!(matches!(
+            find_project_root(Path::new(r"C:\")),
+            Err(HuskyError::GitDirNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        assert_eq!(HuskyError::GitDirNotFound("/".to_string()).exit_code(), 2);
+        assert_eq!(HuskyError::Io(denied).exit_code(), 3);
+        assert_eq!(HuskyError::HookNotReadable(PathBuf::new()).exit_code(), 3);
+        assert_eq!(HuskyError::Config("bad".to_string()).exit_code(), 4);
+        assert_eq!(HuskyError::OutOfSync(Vec::new()).exit_code(), 5);
+        assert_eq!(HuskyError::EmptyUserHook(PathBuf::new()).exit_code(), 1);
+    }
+
+    #[test]
+    fn test_discovery_start_without_out_dir() {
+        assert_eq!(
+            discovery_start(Some("target/out".into())).unwrap(),
+            Path::new("target/out")
+        );
+        let start_dir = discovery_start(None).unwrap();
+        assert_eq!(start_dir, env::current_dir().unwrap());
+        // The crate's own checkout, or nothing when built from a tarball, but never a panic
+        assert!(discover_git_dir(&start_dir).is_ok());
+    }
+
+    #[test]
+    fn test_find_git_dir_from_inside_git_dir() {
+        let dir = temp_dir("inside-git-dir");
+        let git_dir = dir.join(".git");
+        // A hooks repository checked out into `.git/hooks`
+        let hooks_checkout = git_dir.join("hooks");
+        fs::create_dir_all(hooks_checkout.join(".git")).unwrap();
+        fs::create_dir_all(hooks_checkout.join("target").join("out")).unwrap();
+
+        for start in [
+            hooks_checkout.join("target").join("out"),
+            hooks_checkout.clone(),
+            git_dir.clone(),
+        ] {
+            assert_eq!(
+                discover_git_dir(&start).unwrap(),
+                Some((git_dir.clone(), dir.clone())),
+                "{}",
+                start.display()
+            );
+        }
+        assert_eq!(find_project_root(&git_dir).unwrap(), dir);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_discover_linked_worktree() {
+        let dir = temp_repo("linked-worktree", &[("pre-commit", TEST_HOOK)]);
+        fs::remove_dir_all(dir.join(".git")).unwrap();
+        git(&dir, &["init", "--quiet"]);
+        git(
+            &dir,
+            &[
+                "-c",
+                "user.name=husky",
+                "-c",
+                "user.email=husky@example.com",
+                "commit",
+                "--quiet",
+                "--allow-empty",
+                "--no-verify",
+                "-m",
+                "init",
+            ],
+        );
+        let linked = dir.join("linked");
+        git(
+            &dir,
+            &["worktree", "add", "--quiet", linked.to_str().unwrap()],
+        );
+        let main_git_dir = fs::canonicalize(dir.join(".git")).unwrap();
+
+        let (git_dir, work_tree) = discover_git_dir(&linked.join("target")).unwrap().unwrap();
+        assert_eq!(work_tree, linked);
+        assert_ne!(git_dir, main_git_dir);
+        // Named by GIT_DIR, the worktree's git dir still leads back to it
+        assert_eq!(
+            fs::canonicalize(find_project_root(&git_dir).unwrap()).unwrap(),
+            fs::canonicalize(&linked).unwrap()
+        );
+        assert_eq!(common_git_dir(git_dir).unwrap(), main_git_dir);
+        assert_eq!(common_git_dir(main_git_dir.clone()).unwrap(), main_git_dir);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_relative_git_dir() {
+        let dir = temp_dir("relative-git-dir");
+        let out_dir = dir.join("target").join("debug").join("build").join("out");
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+
+        assert_eq!(
+            resolve_relative_git_dir(Path::new(".git"), &out_dir),
+            Some(dir.join(".git"))
+        );
+        assert_eq!(
+            resolve_relative_git_dir(Path::new("missing.git"), &out_dir),
+            None
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_work_tree_override() {
+        assert_eq!(work_tree_override(None), None);
+        assert_eq!(work_tree_override(Some(".".into())), None);
+        assert_eq!(work_tree_override(Some("../work".into())), None);
+        let absolute = env::temp_dir().join("work");
+        assert_eq!(
+            work_tree_override(Some(absolute.clone().into_os_string())),
+            Some(absolute)
+        );
+    }
+
+    #[test]
+    fn test_is_self_build() {
+        let dir = temp_dir("self-build");
+        let dependency = dir.join("vendor").join("husky-rs");
+        fs::create_dir_all(&dependency).unwrap();
+        assert!(is_self_build(&dir, &dir));
+        assert!(is_self_build(&dir.join("vendor").join(".."), &dir));
+        assert!(!is_self_build(&dependency, &dir));
+        assert!(!is_self_build(&dir.join("missing"), &dir));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,6591 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Why hooks could not be installed.
+#[derive(Debug)]
+pub enum HuskyError {
+    GitDirNotFound(String),
+    Io(io::Error),
+    Env(env::VarError),
+    EmptyUserHook(PathBuf),
+    AmbiguousHook(String, Vec<PathBuf>),
+    PathResolution(PathBuf, String, io::Error),
+    Config(String),
+    OutOfSync(Vec<PathBuf>),
+    GitDirUnreadable(PathBuf, io::Error),
+    DiskFull(PathBuf),
+    Generator(PathBuf, String),
+    MissingShebang(PathBuf),
+    SyntaxError(PathBuf, String),
+    SelfTest(String),
+    MissingRequiredHooks(PathBuf, Vec<String>),
+    HookNotReadable(PathBuf),
+    HookTooLarge(PathBuf, u64, u64),
+    // Every hook that failed validation, so the first one found doesn't hide the others
+    InvalidHooks(Vec<HuskyError>),
+}
+
+impl std::fmt::Display for HuskyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HuskyError::GitDirNotFound(path) => write!(
+                f,
+                "Git directory not found in '{}' or its parent directories",
+                path
+            ),
+            HuskyError::Io(err) => write!(f, "IO error: {}", err),
+            HuskyError::Env(err) => write!(f, "Environment variable error: {}", err),
+            HuskyError::EmptyUserHook(path) => {
+                write!(f, "User hook script is empty: '{}'", path.display())
+            }
+            HuskyError::PathResolution(path, context, err) => write!(
+                f,
+                "Failed to resolve '{}' ({}): {}",
+                path.display(),
+                context,
+                err
+            ),
+            HuskyError::Config(message) => write!(f, "Invalid husky-rs configuration: {}", message),
+            HuskyError::GitDirUnreadable(path, err) => write!(
+                f,
+                "Git directory '{}' cannot be read ({}), check its permissions",
+                path.display(),
+                err
+            ),
+            HuskyError::Generator(path, reason) => write!(
+                f,
+                "Generating the hook from '{}' failed: {}",
+                path.display(),
+                reason
+            ),
+            HuskyError::DiskFull(path) => write!(
+                f,
+                "No space left on the device while writing '{}', the previous hook was kept",
+                path.display()
+            ),
+            HuskyError::OutOfSync(paths) => {
+                let paths: Vec<_> = paths
+                    .iter()
+                    .map(|path| format!("'{}'", path.display()))
+                    .collect();
+                write!(
+                    f,
+                    "Installed hooks are out of sync, run `cargo build` without HUSKY_CHECK: {}",
+                    paths.join(", ")
+                )
+            }
+            HuskyError::MissingShebang(path) => write!(
+                f,
+                "Hook '{}' has no shebang, which `require_shebang` demands on its first line",
+                path.display()
+            ),
+            HuskyError::SyntaxError(path, message) => {
+                write!(f, "Hook '{}' does not parse: {}", path.display(), message)
+            }
+            HuskyError::SelfTest(reason) => write!(f, "Self-test failed: {}", reason),
+            HuskyError::HookNotReadable(path) => write!(
+                f,
+                "Hook source '{}' cannot be read, check its permissions",
+                path.display()
+            ),
+            HuskyError::HookTooLarge(path, size, limit) => write!(
+                f,
+                "Hook source '{}' is {} bytes, more than `max_hook_size` allows ({})",
+                path.display(),
+                size,
+                limit
+            ),
+            HuskyError::MissingRequiredHooks(dir, names) => write!(
+                f,
+                "Hooks listed in `required_hooks` have no source in '{}': {}",
+                dir.display(),
+                names.join(", ")
+            ),
+            HuskyError::InvalidHooks(errors) => {
+                let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "{} hooks are invalid: {}",
+                    errors.len(),
+                    errors.join("; ")
+                )
+            }
+            HuskyError::AmbiguousHook(name, sources) => {
+                let sources: Vec<_> = sources
+                    .iter()
+                    .map(|path| format!("'{}'", path.display()))
+                    .collect();
+                write!(
+                    f,
+                    "Multiple sources found for hook '{}': {}",
+                    name,
+                    sources.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for HuskyError {}
+
+// Fails with the only problem as is, or with all of them together
+fn validation_result(mut problems: Vec<HuskyError>) -> Result<()> {
+    match problems.len() {
+        0 => Ok(()),
+        1 => Err(problems.remove(0)),
+        _ => Err(HuskyError::InvalidHooks(problems)),
+    }
+}
+
+impl From<io::Error> for HuskyError {
+    fn from(err: io::Error) -> Self {
+        HuskyError::Io(err)
+    }
+}
+
+impl From<env::VarError> for HuskyError {
+    fn from(err: env::VarError) -> Self {
+        HuskyError::Env(err)
+    }
+}
+
+pub(crate) type Result<T> = std::result::Result<T, HuskyError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum LogLevel {
+    Silent,
+    Error,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    const DEFAULT: LogLevel = LogLevel::Info;
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "silent" => Some(LogLevel::Silent),
+            "error" => Some(LogLevel::Error),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+}
+
+/// Installation options, as read from a project's manifest by [`Config::load`] or given directly
+/// to [`Config::from_metadata`]. `Config::default()` has the defaults both apply.
+#[derive(Debug, Clone)]
+pub struct Config {
+    // Leave any hook already present at the destination untouched
+    only_missing: bool,
+    // Also install hooks that only run in server (receiving) repositories
+    server_hooks: bool,
+    // Make shell hooks abort on the first failing command
+    strict_body: bool,
+    // Where hook sources live instead of `.husky/hooks`, relative to the project root
+    hooks_src: Option<PathBuf>,
+    // A `.tar.gz`/`.tgz` or `.zip` archive to read hooks from, needs the `archive` feature
+    hooks_archive: Option<PathBuf>,
+    // Warn when a hook's interpreter cannot be found on `PATH` at install time
+    check_interpreters: bool,
+    // Parse shell hooks with their shell's `-n` before installing, failing on syntax errors
+    check_syntax: bool,
+    // Warn when git ignores the hook sources, so they would never reach teammates
+    check_ignored: bool,
+    // Only compare installed hooks against their sources and fail if any differ (for CI)
+    check: bool,
+    // Appended to every hook after the user's body
+    footer: Option<String>,
+    // Run the footer from an `EXIT` trap, so shell hooks run it even when the body fails
+    footer_on_exit: bool,
+    // Name the source file(s) of each hook in its header, on by default
+    source_comment: bool,
+    // How often to retry writing a hook that is briefly locked, e.g. by a virus scanner
+    write_retries: u32,
+    // Extended regex matching a ticket ID in the branch name; generates a `prepare-commit-msg`
+    // hook prefixing commit messages with it, unless the project has its own
+    ticket_pattern: Option<String>,
+    header_style: HeaderStyle,
+    header_version: HeaderVersion,
+    // Wrap the header's prose at this many columns, for linters flagging long comment lines
+    header_width: Option<usize>,
+    // Note when each hook was written in its header, which rewrites hooks on every build
+    header_timestamp: bool,
+    // Also install hooks placed directly in `.husky`, like Node husky v9 does
+    husky_dir_hooks: bool,
+    // Copy each hook body to `.git/hooks/.husky/<name>` and install a launcher exec'ing it
+    exec_payload: bool,
+    // Keep the leading comment block of a hook verbatim, ahead of any generated lines
+    preserve_comments: bool,
+    // The only hooks installed while husky-rs is just a dev-dependency of the project, so only
+    // set then
+    dev_hooks: Option<Vec<String>>,
+    // Arguments for the built-in `cargo fmt` pre-commit hook, when enabled with `fmt_check`
+    fmt_check: Option<Vec<String>>,
+    // For hooks without a recognized shebang; bash when unset
+    default_shebang: Option<String>,
+    // Fail on hooks without a shebang instead of falling back to `default_shebang`
+    require_shebang: bool,
+    // Replaces the shebang of every shell hook, so they all run with the same shell
+    canonical_shebang: Option<String>,
+    // Extends `canonical_shebang` to hooks for any other interpreter
+    force_canonical_shebang: bool,
+    // Write a summary of the install to `$OUT_DIR/husky-report.txt`
+    report: bool,
+    // Hook names installed on top of git's own, for hooks run by the project's tooling
+    extra_hooks: Vec<String>,
+    // Hooks copied byte for byte, without a header; binary sources always are
+    opaque_hooks: Vec<String>,
+    // Hooks the build fails without, so a team can make sure they're never dropped
+    required_hooks: Vec<String>,
+    // Copies every hook as `opaque_hooks` does, for users who want no shebang handling at all
+    raw: bool,
+    // Sources above this many bytes fail instead of being installed, raw or not; 10 MiB unless
+    // configured
+    max_hook_size: u64,
+    // Refs the generated server-side `update` hook refuses to delete or rewind
+    protected_refs: Vec<String>,
+    // Permission bits for installed hooks on Unix, `0o755` minus the umask when unset
+    hook_mode: Option<u32>,
+    // Git for Windows' own bash, which shell hooks are pinned to when `git_bash` is set
+    git_bash: Option<PathBuf>,
+    // Name hooks after their source file, like `pre-commit.sh`, for tools other than git
+    export: bool,
+    // Remove the installed hooks instead of installing them
+    clean: bool,
+    // List the installed hooks instead of installing them
+    list: bool,
+    // Write hooks to `.husky-staging` inside the hooks directory, for review before going live
+    review: bool,
+    // Copy reviewed hooks from `.husky-staging` into the hooks directory, if they're unchanged
+    promote: bool,
+    // Fail on problems that are otherwise warned about, like a hook with several sources
+    strict: bool,
+    empty_hook: EmptyHookPolicy,
+    // Run the commands named by `# husky:generate:` sources, whose output becomes the hook
+    generators: bool,
+    // Installs each hook as the fragment `<hook>.d/<owner>`, run by a shared dispatcher, so
+    // several projects can share one hooks directory
+    owner: Option<String>,
+    // Whether the dispatcher of each hook stops at the first failing fragment
+    on_error: OnErrorPolicy,
+    // The `*.sh` files found in `_lib` at install time, sourced by every shell hook
+    libs: Vec<String>,
+    // Where hook bodies are kept between builds, set at install time
+    read_cache: Option<PathBuf>,
+}
+
+// The husky-rs version named in the header, which changes every hook on each upgrade unless
+// pinned or left out
+#[derive(Debug, Default, Clone, PartialEq)]
+enum HeaderVersion {
+    #[default]
+    Current,
+    Pinned(String),
+    Omitted,
+}
+
+impl HeaderVersion {
+    fn text(&self) -> Option<&str> {
+        match self {
+            HeaderVersion::Current => Some(env!("CARGO_PKG_VERSION")),
+            HeaderVersion::Pinned(version) => Some(version),
+            HeaderVersion::Omitted => None,
+        }
+    }
+}
+
+// What a dispatcher does when one of its fragments fails
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum OnError {
+    // Fail the hook without running the remaining fragments
+    #[default]
+    Abort,
+    // Report the failure and run the remaining fragments
+    Continue,
+}
+
+// `on_error` for every hook, and the hooks listed in `continue_on_error` or `abort_on_error`
+// as exceptions to it
+#[derive(Debug, Default, Clone)]
+struct OnErrorPolicy {
+    default: OnError,
+    continues: Vec<String>,
+    aborts: Vec<String>,
+}
+
+impl OnErrorPolicy {
+    fn for_hook(&self, name: &str) -> OnError {
+        if self.continues.iter().any(|hook| hook == name) {
+            OnError::Continue
+        } else if self.aborts.iter().any(|hook| hook == name) {
+            OnError::Abort
+        } else {
+            self.default
+        }
+    }
+}
+
+// What to do with a hook whose sources have nothing but blank lines and a shebang
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum EmptyHookPolicy {
+    #[default]
+    Error,
+    // Warn and leave the hook out
+    Warn,
+    // Leave the hook out quietly
+    Skip,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum HeaderStyle {
+    // A comment block with the version, homepage and sources
+    #[default]
+    Full,
+    // A single `# This hook was set by husky-rs vX.Y.Z` line
+    Minimal,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::from_settings(&Settings::default(), None).expect("the defaults are valid")
+    }
+}
+
+impl Config {
+    /// Reads `[package.metadata.husky]` (and `[workspace.metadata.husky]`) from `manifest`, with
+    /// `HUSKY_<KEY>` environment variables taking precedence; a missing manifest leaves the
+    /// defaults.
+    pub fn load(manifest: &Path) -> Result<Self> {
+        Config::from_settings(&Settings::load(manifest)?, Some(manifest))
+    }
+
+    pub(crate) fn from_settings(settings: &Settings, manifest: Option<&Path>) -> Result<Self> {
+        Ok(Config {
+            // Forcing reinstalls every hook, whatever `only_missing` says
+            only_missing: settings.flag("only_missing")?
+                && !(settings.env && env_flag("HUSKY_FORCE")),
+            server_hooks: settings.flag("server_hooks")?,
+            strict_body: settings.flag("strict_body")?,
+            hooks_src: settings.path("hooks_src")?,
+            hooks_archive: settings.path("hooks_archive")?,
+            check_interpreters: settings.flag("check_interpreters")?,
+            check_syntax: settings.flag("check_syntax")?,
+            check_ignored: settings.flag_or("check_ignored", true)?,
+            check: settings.switch("check")?,
+            clean: settings.switch("clean")?,
+            export: match settings.env {
+                true => env::var_os("HUSKY_EXPORT_DIR").is_some_and(|dir| !dir.is_empty()),
+                false => settings.flag("export")?,
+            },
+            list: settings.switch("list")?,
+            review: settings.flag("review")?,
+            promote: settings.switch("promote")?,
+            strict: settings.switch("strict")?,
+            require_shebang: settings.flag("require_shebang")?,
+            canonical_shebang: match settings.string("canonical_shebang")? {
+                Some(shebang) if !is_shebang(&shebang) => {
+                    return Err(HuskyError::Config(format!(
+                        "`canonical_shebang` must be a `#!` line naming a program, got '{}'",
+                        shebang
+                    )))
+                }
+                shebang => shebang.map(|shebang| shebang.trim().to_string()),
+            },
+            force_canonical_shebang: settings.flag("force_canonical_shebang")?,
+            empty_hook: match settings.string("empty_hook")?.as_deref() {
+                None | Some("error") => EmptyHookPolicy::Error,
+                Some("warn") => EmptyHookPolicy::Warn,
+                Some("skip") => EmptyHookPolicy::Skip,
+                Some(other) => {
+                    return Err(HuskyError::Config(format!(
+                        "`empty_hook` must be `error`, `warn` or `skip`, got '{}'",
+                        other
+                    )))
+                }
+            },
+            generators: settings.flag("generators")?,
+            footer: settings.string("footer")?,
+            footer_on_exit: settings.flag("footer_on_exit")?,
+            source_comment: settings.flag_or("source_comment", true)?,
+            write_retries: match settings.integer("write_retries")? {
+                None => DEFAULT_WRITE_RETRIES,
+                Some(retries) => u32::try_from(retries).map_err(|_| {
+                    HuskyError::Config("`write_retries` must not be negative".to_string())
+                })?,
+            },
+            ticket_pattern: settings.string("ticket_pattern")?,
+            husky_dir_hooks: settings.flag("husky_dir_hooks")?,
+            exec_payload: settings.flag("exec_payload")?,
+            preserve_comments: settings.flag("preserve_comments")?,
+            dev_hooks: match (
+                settings.array("dev_hooks")?,
+                manifest.filter(|path| path.is_file()),
+            ) {
+                (Some(hooks), Some(manifest))
+                    if is_dev_dependency(&fs::read_to_string(manifest)?) =>
+                {
+                    Some(hooks)
+                }
+                _ => None,
+            },
+            report: settings.flag("report")?,
+            extra_hooks: settings.array("extra_hooks")?.unwrap_or_default(),
+            opaque_hooks: settings.array("opaque_hooks")?.unwrap_or_default(),
+            required_hooks: settings.array("required_hooks")?.unwrap_or_default(),
+            raw: settings.flag("raw")?,
+            max_hook_size: match settings.integer("max_hook_size")? {
+                None => DEFAULT_MAX_HOOK_SIZE,
+                Some(size) => u64::try_from(size)
+                    .ok()
+                    .filter(|&size| size > 0)
+                    .ok_or_else(|| {
+                        HuskyError::Config("`max_hook_size` must be positive".to_string())
+                    })?,
+            },
+            protected_refs: protected_refs(settings)?,
+            hook_mode: settings
+                .string("hook_mode")?
+                .map(|mode| {
+                    parse_mode(&mode).ok_or_else(|| {
+                        HuskyError::Config(format!(
+                            "`hook_mode` must be an octal mode like \"0755\", got '{}'",
+                            mode
+                        ))
+                    })
+                })
+                .transpose()?,
+            git_bash: match settings.flag("git_bash")? {
+                true => find_git_bash(),
+                false => None,
+            },
+            default_shebang: Some(
+                settings
+                    .string("default_shebang")?
+                    .unwrap_or_else(host_default_shebang),
+            ),
+            fmt_check: match settings.flag("fmt_check")? {
+                false => None,
+                true => Some(settings.array("fmt_args")?.unwrap_or_else(|| {
+                    DEFAULT_FMT_ARGS.iter().map(|arg| arg.to_string()).collect()
+                })),
+            },
+            header_style: match settings.string("header_style")?.as_deref() {
+                None | Some("full") => HeaderStyle::Full,
+                Some("minimal") => HeaderStyle::Minimal,
+                Some(other) => {
+                    return Err(HuskyError::Config(format!(
+                        "`header_style` must be `full` or `minimal`, got '{}'",
+                        other
+                    )))
+                }
+            },
+            header_version: header_version(settings)?,
+            header_timestamp: settings.flag("header_timestamp")?,
+            header_width: match settings.integer("header_width")? {
+                None => None,
+                Some(width) => Some(
+                    usize::try_from(width)
+                        .ok()
+                        .filter(|&width| width > 0)
+                        .ok_or_else(|| {
+                            HuskyError::Config("`header_width` must be positive".to_string())
+                        })?,
+                ),
+            },
+            owner: owner(settings)?,
+            on_error: on_error(settings)?,
+            libs: Vec::new(),
+            read_cache: None,
+        })
+    }
+}
+
+// Cargo doesn't tell a build script which kind of dependency it was built as, but the project's
+// manifest does: husky-rs only under `[dev-dependencies]` (or a target's), nowhere else
+fn is_dev_dependency(manifest: &str) -> bool {
+    let name = env!("CARGO_PKG_NAME");
+    let mut table = String::new();
+    let mut tables = Vec::new();
+    for line in manifest.lines() {
+        let line = strip_comment(line).trim();
+        if let Some(header) = line.strip_prefix('[') {
+            table = header.trim_matches(['[', ']']).trim().to_string();
+            // `[dev-dependencies.husky-rs]`
+            if let Some(parent) = table.strip_suffix(&format!(".{}", name)) {
+                tables.push(parent.to_string());
+            }
+            continue;
+        }
+        // `husky-rs = "0.1"`, `husky-rs = { path = ".." }` or `husky-rs.workspace = true`
+        let key = line.split(['=', '.']).next().unwrap_or("").trim();
+        if key.trim_matches('"') == name && line.contains('=') {
+            tables.push(table.clone());
+        }
+    }
+    !tables.is_empty()
+        && tables
+            .iter()
+            .all(|table| table.ends_with("dev-dependencies"))
+}
+
+// Names a file in every `<hook>.d` directory, so it must be a plain file name
+fn owner(settings: &Settings) -> Result<Option<String>> {
+    let owner = settings.string("owner")?;
+    if let Some(owner) = owner.as_deref().filter(|owner| {
+        owner.is_empty()
+            || owner.starts_with('.')
+            || !owner
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+    }) {
+        return Err(HuskyError::Config(format!(
+            "`owner` must be a plain name like `my-crate`, got '{}'",
+            owner
+        )));
+    }
+    Ok(owner)
+}
+
+fn on_error(settings: &Settings) -> Result<OnErrorPolicy> {
+    let default = match settings.string("on_error")?.as_deref() {
+        None | Some("abort") => OnError::Abort,
+        Some("continue") => OnError::Continue,
+        Some(other) => {
+            return Err(HuskyError::Config(format!(
+                "`on_error` must be `abort` or `continue`, got '{}'",
+                other
+            )))
+        }
+    };
+    let continues = settings.array("continue_on_error")?.unwrap_or_default();
+    let aborts = settings.array("abort_on_error")?.unwrap_or_default();
+    if let Some(both) = continues.iter().find(|hook| aborts.contains(hook)) {
+        return Err(HuskyError::Config(format!(
+            "'{}' is listed in both `continue_on_error` and `abort_on_error`",
+            both
+        )));
+    }
+    Ok(OnErrorPolicy {
+        default,
+        continues,
+        aborts,
+    })
+}
+
+// `false` leaves the version out, a string pins it, `true` (the default) uses this version
+fn header_version(settings: &Settings) -> Result<HeaderVersion> {
+    const KEY: &str = "header_version";
+    if let Some(value) = settings.var(KEY) {
+        return Ok(match value.trim().to_ascii_lowercase().as_str() {
+            _ if !parse_flag(&value) => HeaderVersion::Omitted,
+            "1" | "true" | "yes" | "on" => HeaderVersion::Current,
+            _ => HeaderVersion::Pinned(value.trim().to_string()),
+        });
+    }
+    match settings.table.get(KEY) {
+        None | Some(ConfigValue::Bool(true)) => Ok(HeaderVersion::Current),
+        Some(ConfigValue::Bool(false)) => Ok(HeaderVersion::Omitted),
+        Some(ConfigValue::String(version)) => Ok(HeaderVersion::Pinned(version.clone())),
+        Some(_) => Err(HuskyError::Config(format!(
+            "`{}` must be a boolean or a version string",
+            KEY
+        ))),
+    }
+}
+
+// Accepts `755`, `0755` and `0o755`
+fn parse_mode(value: &str) -> Option<u32> {
+    let digits = value.trim();
+    let digits = digits.strip_prefix("0o").unwrap_or(digits);
+    if digits.is_empty() || digits.len() > 4 {
+        return None;
+    }
+    u32::from_str_radix(digits, 8).ok()
+}
+
+// Spliced into the `update` template, so only plain ref names are accepted
+fn protected_refs(settings: &Settings) -> Result<Vec<String>> {
+    let refs = settings.array("protected_refs")?.unwrap_or_default();
+    if let Some(invalid) = refs.iter().find(|name| {
+        name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/._-".contains(c))
+    }) {
+        return Err(HuskyError::Config(format!(
+            "`protected_refs` entry '{}' is not a plain ref name",
+            invalid
+        )));
+    }
+    Ok(refs)
+}
+
+// Looks settings up in the environment first, then in the manifest table
+#[derive(Default)]
+pub(crate) struct Settings {
+    pub(crate) table: BTreeMap<String, ConfigValue>,
+    // Whether `HUSKY_<KEY>` variables are read at all, as they are by the build script
+    pub(crate) env: bool,
+}
+
+impl Settings {
+    pub(crate) fn load(manifest: &Path) -> Result<Self> {
+        // The workspace's table is the base, the package's settings override it key by key
+        let mut table = match workspace_manifest(manifest) {
+            Some(workspace) => read_metadata_table(&workspace, WORKSPACE_METADATA_TABLE)?,
+            None => BTreeMap::new(),
+        };
+        if manifest.is_file() {
+            table.extend(read_metadata_table(manifest, METADATA_TABLE)?);
+        }
+        Ok(Settings { table, env: true })
+    }
+
+    fn var(&self, key: &str) -> Option<String> {
+        self.env.then(|| env::var(env_var_name(key)).ok()).flatten()
+    }
+
+    // A switch the environment sets for one build, like `HUSKY_CHECK`, which has no place in
+    // the committed manifest but can be given like any other setting without the environment
+    fn switch(&self, key: &str) -> Result<bool> {
+        match self.env {
+            true => Ok(env_flag(&env_var_name(key))),
+            false => self.flag(key),
+        }
+    }
+
+    pub(crate) fn flag(&self, key: &str) -> Result<bool> {
+        self.flag_or(key, false)
+    }
+
+    fn flag_or(&self, key: &str, default: bool) -> Result<bool> {
+        if let Some(value) = self.var(key) {
+            return Ok(parse_flag(&value));
+        }
+        match self.table.get(key) {
+            None => Ok(default),
+            Some(ConfigValue::Bool(value)) => Ok(*value),
+            Some(_) => Err(HuskyError::Config(format!("`{}` must be a boolean", key))),
+        }
+    }
+
+    fn integer(&self, key: &str) -> Result<Option<i64>> {
+        if let Some(value) = self.var(key) {
+            return value.trim().parse().map(Some).map_err(|_| {
+                HuskyError::Config(format!("`{}` must be an integer, got '{}'", key, value))
+            });
+        }
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(ConfigValue::Integer(value)) => Ok(Some(*value)),
+            Some(_) => Err(HuskyError::Config(format!("`{}` must be an integer", key))),
+        }
+    }
+
+    // From the environment, a list is split on whitespace
+    pub(crate) fn array(&self, key: &str) -> Result<Option<Vec<String>>> {
+        if let Some(value) = self.var(key) {
+            return Ok(Some(value.split_whitespace().map(str::to_string).collect()));
+        }
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(ConfigValue::Array(values)) => Ok(Some(values.clone())),
+            Some(_) => Err(HuskyError::Config(format!(
+                "`{}` must be an array of strings",
+                key
+            ))),
+        }
+    }
+
+    fn string(&self, key: &str) -> Result<Option<String>> {
+        if let Some(value) = self.var(key) {
+            return Ok(Some(value));
+        }
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(ConfigValue::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(HuskyError::Config(format!("`{}` must be a string", key))),
+        }
+    }
+
+    fn path(&self, key: &str) -> Result<Option<PathBuf>> {
+        self.string(key)?
+            .map(|value| {
+                expand_path(&value, |name| env::var(name).ok())
+                    .map(|path| normalize_path(&path))
+                    .map_err(|err| HuskyError::Config(format!("`{}`: {}", key, err)))
+            })
+            .transpose()
+    }
+}
+
+// Expands a leading `~` and `$VAR`/`${VAR}` references. This is only meant for configured
+// paths; hook bodies are left for the shell to expand at run time.
+fn expand_path(
+    value: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> std::result::Result<PathBuf, String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        let home = lookup("HOME")
+            .or_else(|| lookup("USERPROFILE"))
+            .ok_or("cannot expand `~` without HOME")?;
+        expanded.push_str(&home);
+        rest = &rest[1..];
+    }
+
+    while let Some(index) = rest.find('$') {
+        expanded.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+        let (name, remainder) = match rest.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced.find('}').ok_or("unterminated `${`")?;
+                (&braced[..end], &braced[end + 1..])
+            }
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                rest.split_at(end)
+            }
+        };
+        // A lone `$` is kept as is
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        let value = lookup(name).ok_or_else(|| format!("`${}` is not set", name))?;
+        expanded.push_str(&value);
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+// Lexically, so `.husky/hooks/`, `./.husky/hooks` and `.husky/x/../hooks` name the same
+// directory in discovery and rerun directives, whether or not it exists yet. Separators are
+// unified by `components`, which accepts both `/` and `\` on Windows.
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // `..` at the root is the root itself
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+fn env_var_name(key: &str) -> String {
+    format!("HUSKY_{}", key.to_ascii_uppercase())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ConfigValue {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+    Array(Vec<String>),
+}
+
+// The manifest of the workspace the project belongs to: `CARGO_WORKSPACE_DIR`'s if set, else the
+// closest one with a `[workspace]` table, starting with the project's own
+fn workspace_manifest(manifest: &Path) -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("CARGO_WORKSPACE_DIR").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("Cargo.toml")).filter(|path| path.is_file());
+    }
+    manifest
+        .parent()?
+        .ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|path| fs::read_to_string(path).is_ok_and(|content| is_workspace_manifest(&content)))
+}
+
+fn is_workspace_manifest(content: &str) -> bool {
+    content.lines().any(|line| {
+        let line = strip_comment(line).trim();
+        line == "[workspace]" || line.starts_with("[workspace.")
+    })
+}
+
+// Reads a table such as `[package.metadata.husky]` from a manifest. Only the subset of TOML
+// needed for settings is understood: booleans, integers, strings and arrays of strings.
+fn read_metadata_table(manifest: &Path, table: &str) -> Result<BTreeMap<String, ConfigValue>> {
+    let content = fs::read_to_string(manifest)?;
+    parse_table(&content, Some(table))
+        .map_err(|err| HuskyError::Config(format!("{} in '{}'", err, manifest.display())))
+}
+
+// Without a table name, the content is the body of one
+pub(crate) fn parse_table(
+    content: &str,
+    table: Option<&str>,
+) -> std::result::Result<BTreeMap<String, ConfigValue>, String> {
+    let mut values = BTreeMap::new();
+    let mut in_table = table.is_none();
+    let mut lines = content.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let line = strip_comment(line).trim();
+        if line.starts_with('[') {
+            in_table = table == Some(line.trim_matches(['[', ']']).trim());
+            continue;
+        }
+        if !in_table || line.is_empty() {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("expected `key = value` on line {}", index + 1))?;
+        let key = key.trim().trim_matches('"');
+        let mut value = value.trim().to_string();
+        // Arrays may span several lines
+        while bracket_depth(&value) > 0 {
+            let (_, next) = lines
+                .next()
+                .ok_or_else(|| format!("unterminated array for `{}`", key))?;
+            value.push(' ');
+            value.push_str(strip_comment(next).trim());
+        }
+        let value = parse_value(&value)
+            .map_err(|err| format!("invalid `{}` on line {}: {}", key, index + 1, err))?;
+        values.insert(key.to_string(), value);
+    }
+    Ok(values)
+}
+
+// Yields the characters outside of string literals, with their byte offsets
+fn unquoted(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut quote = None;
+    let mut escaped = false;
+    text.char_indices().filter(move |&(_, c)| match quote {
+        Some(_) if escaped => {
+            escaped = false;
+            false
+        }
+        Some('"') if c == '\\' => {
+            escaped = true;
+            false
+        }
+        Some(open) => {
+            if c == open {
+                quote = None;
+            }
+            false
+        }
+        None if c == '"' || c == '\'' => {
+            quote = Some(c);
+            false
+        }
+        None => true,
+    })
+}
+
+fn strip_comment(line: &str) -> &str {
+    unquoted(line)
+        .find(|&(_, c)| c == '#')
+        .map_or(line, |(index, _)| &line[..index])
+}
+
+fn bracket_depth(text: &str) -> i32 {
+    unquoted(text)
+        .map(|(_, c)| match c {
+            '[' => 1,
+            ']' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+fn parse_value(text: &str) -> std::result::Result<ConfigValue, String> {
+    match text {
+        "true" => return Ok(ConfigValue::Bool(true)),
+        "false" => return Ok(ConfigValue::Bool(false)),
+        _ => {}
+    }
+
+    if let Some(items) = text
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        let mut array = Vec::new();
+        let mut rest = items.trim();
+        while !rest.is_empty() {
+            let (item, remainder) = parse_string(rest)?;
+            array.push(item);
+            rest = remainder.trim_start();
+            if let Some(remainder) = rest.strip_prefix(',') {
+                rest = remainder.trim_start();
+            } else if !rest.is_empty() {
+                return Err("expected `,` between array items".to_string());
+            }
+        }
+        return Ok(ConfigValue::Array(array));
+    }
+
+    if text.starts_with(['"', '\'']) {
+        let (value, rest) = parse_string(text)?;
+        if !rest.trim().is_empty() {
+            return Err(format!("unexpected `{}` after string", rest.trim()));
+        }
+        return Ok(ConfigValue::String(value));
+    }
+
+    text.replace('_', "")
+        .parse()
+        .map(ConfigValue::Integer)
+        .map_err(|_| format!("unsupported value `{}`", text))
+}
+
+// Parses a leading basic ("...") or literal ('...') string, returning it and the rest of the input
+fn parse_string(text: &str) -> std::result::Result<(String, &str), String> {
+    let mut chars = text.char_indices();
+    let quote = match chars.next() {
+        Some((_, quote @ ('"' | '\''))) => quote,
+        _ => return Err(format!("expected a string, found `{}`", text)),
+    };
+    let mut value = String::new();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            c if c == quote => return Ok((value, &text[index + 1..])),
+            '\\' if quote == '"' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, escaped @ ('"' | '\\'))) => value.push(escaped),
+                _ => return Err("unsupported escape sequence".to_string()),
+            },
+            c => value.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+enum InstallOutcome {
+    Installed,
+    UpToDate,
+    // The sources have nothing to run, and `empty_hook` doesn't make that an error
+    Empty,
+    // The destination differs, but `check` forbids touching it
+    OutOfSync(PathBuf),
+}
+
+// What a run would do with a hook, decided before anything is read or written
+#[derive(Debug, PartialEq)]
+enum HookAction {
+    Install,
+    // The destination already exists and `only_missing` is set
+    Keep,
+    SkipDisabled,
+    SkipServerSide,
+    // Not in `dev_hooks`, with husky-rs only a dev-dependency
+    SkipDevOnly,
+}
+
+#[derive(Debug)]
+struct PlannedHook<'a> {
+    name: &'a str,
+    sources: &'a [HookSource],
+    dst: PathBuf,
+    action: HookAction,
+}
+
+static LOG_LEVEL: OnceLock<LogLevel> = OnceLock::new();
+
+pub(crate) fn log_level() -> LogLevel {
+    *LOG_LEVEL.get_or_init(|| match env::var("HUSKY_LOG") {
+        Ok(value) => LogLevel::parse(&value).unwrap_or_else(|| {
+            eprintln!(
+                "Unknown HUSKY_LOG level '{}', expected one of silent|error|info|debug",
+                value
+            );
+            LogLevel::DEFAULT
+        }),
+        Err(_) => LogLevel::DEFAULT,
+    })
+}
+
+// Errors go to stderr, everything else to stdout (which cargo keeps out of the way)
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        if $level <= $crate::install::log_level() && $crate::install::printing() {
+            if $level == $crate::install::LogLevel::Error {
+                eprintln!($($arg)*);
+            } else {
+                println!($($arg)*);
+            }
+        }
+    };
+}
+
+// A line for cargo, like `cargo:rerun-if-changed=<path>`, which only the build script prints
+macro_rules! cargo {
+    ($($arg:tt)*) => {
+        if $crate::install::printing() {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub(crate) const HUSKY_DIR: &str = ".husky";
+pub(crate) const HUSKY_HOOKS_DIR: &str = "hooks";
+/// Every hook documented in githooks(5), the names hook sources are installed under.
+// A few rarely matter in a developer clone but are still installed when provided:
+// - `reference-transaction` (git >= 2.28) runs on every ref update, so it must stay fast
+// - `fsmonitor-watchman` is only invoked when `core.fsmonitor` points at it
+// - `pre-receive`, `update`, `proc-receive`, `post-receive` and `post-update` only run on the
+//   receiving side of a push, i.e. in server repositories
+pub const VALID_HOOK_NAMES: [&str; 27] = [
+    "applypatch-msg",
+    "pre-applypatch",
+    "post-applypatch",
+    "pre-commit",
+    "pre-merge-commit",
+    "prepare-commit-msg",
+    "commit-msg",
+    "post-commit",
+    "pre-rebase",
+    "post-checkout",
+    "post-merge",
+    "pre-push",
+    "pre-receive",
+    "update",
+    "proc-receive",
+    "post-receive",
+    "post-update",
+    "reference-transaction",
+    "pre-auto-gc",
+    "post-rewrite",
+    "sendemail-validate",
+    "fsmonitor-watchman",
+    "p4-changelist",
+    "p4-prepare-changelist",
+    "p4-post-changelist",
+    "p4-pre-submit",
+    "post-index-change",
+];
+/// The hooks of [`VALID_HOOK_NAMES`] that only run on the receiving side of a push, skipped unless
+/// `server_hooks` is set.
+pub const SERVER_HOOKS: [&str; 5] = [
+    "pre-receive",
+    "update",
+    "proc-receive",
+    "post-receive",
+    "post-update",
+];
+// Hooks older git versions don't know about, and so never run
+const MIN_GIT_VERSIONS: [(&str, (u32, u32)); 2] = [
+    ("reference-transaction", (2, 28)),
+    ("proc-receive", (2, 29)),
+];
+const METADATA_TABLE: &str = "package.metadata.husky";
+const WORKSPACE_METADATA_TABLE: &str = "workspace.metadata.husky";
+/// The variables CI providers set, `CI` by nearly all of them, any of which makes `skip_in_ci`
+/// install nothing.
+// Only watched with `skip_in_ci`
+pub const CI_ENV_VARS: [&str; 11] = [
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "CIRCLECI",
+    "TRAVIS",
+    "BUILDKITE",
+    "JENKINS_URL",
+    "TF_BUILD",
+    "TEAMCITY_VERSION",
+    "BITBUCKET_BUILD_NUMBER",
+    "APPVEYOR",
+];
+// Extensions that may be appended to hook names for editor syntax highlighting
+const HOOK_EXTENSIONS: [&str; 6] = ["sh", "bash", "py", "rb", "js", "pl"];
+// Sourced by shell hooks when present: `.husky/env` for all hooks, `<hook>.env` for one
+const ENV_FILE: &str = "env";
+const ENV_FILE_EXTENSION: &str = ".env";
+const DEFAULT_WRITE_RETRIES: u32 = 3;
+// Far beyond any script, while still catching e.g. a build artifact copied in by mistake
+const DEFAULT_MAX_HOOK_SIZE: u64 = 10 * 1024 * 1024;
+// Where `exec_payload` keeps hook bodies, named by their content hash so hooks with the same
+// body share one, inside the git hooks directory
+const PAYLOAD_DIR: &str = ".husky";
+const REPORT_FILE: &str = "husky-report.txt";
+// Hook bodies as last read from their sources, one file per hook named by a hash of their
+// paths, inside the git directory or, for hooks installed elsewhere, `OUT_DIR`
+const READ_CACHE_DIR: &str = "husky-read-cache";
+// A source consisting of this directive is replaced by the output of the command it names
+const GENERATE_DIRECTIVE: &str = "# husky:generate:";
+// Declares the git version a hook needs, e.g. `# husky:requires-git: 2.36` for a hook using a
+// newer git command, warned about at install time when the installed git is older
+const REQUIRES_GIT_DIRECTIVE: &str = "# husky:requires-git:";
+// Read for the `eol` attribute of hook sources
+const GITATTRIBUTES_FILE: &str = ".gitattributes";
+// Where hooks are staged without a git directory, inside `.husky`
+pub(crate) const STAGED_HOOKS_DIR: &str = ".staged-hooks";
+// Where hooks wait for review with `review` set, inside the hooks directory
+const REVIEW_DIR: &str = ".husky-staging";
+// With an `owner`, hooks are installed into `<hook>.d` and run by a dispatcher
+const FRAGMENTS_DIR_SUFFIX: &str = ".d";
+// Hooks git feeds on stdin, which the dispatcher replays to every fragment
+const STDIN_HOOKS: [&str; 5] = [
+    "pre-push",
+    "pre-receive",
+    "post-receive",
+    "post-rewrite",
+    "reference-transaction",
+];
+// A single file defining several hooks in `# hook: <name>` sections
+const MULTIPLEXED_FILE: &str = "hooks.sh";
+const SECTION_PREFIX: &str = "# hook:";
+// Glob patterns of files in a hooks directory that are not hooks, e.g. shared helpers
+const IGNORE_FILE: &str = ".ignore";
+// Hidden files, `.DS_Store` among them, and editor swap and backup files are never hooks, even
+// when their name would otherwise parse as a labelled fragment like `pre-commit.sh~`
+const ARTIFACT_PATTERNS: [&str; 3] = [".*", "*.swp", "*~"];
+// Lists hooks not to install, one name per line, with `#` comments
+const DISABLED_FILE: &str = ".disabled";
+// Helpers shell hooks can source, generated next to the installed hooks
+const HELPERS_DIR: &str = "_";
+// Shared shell scripts in the hooks sources, copied next to the helpers and sourced by every
+// shell hook
+const LIB_DIR: &str = "_lib";
+const CHANGED_FILES_HELPER: &str = "changed-files.sh";
+const CHANGED_FILES_FUNCTION: &str = "husky_changed_files";
+const CHANGED_FILES_SCRIPT: &str = r#"# This file was generated by husky-rs and is sourced by shell hooks.
+
+# Lists the files staged for commit, one per line, skipping deletions.
+# Extra arguments are passed to `git diff`, e.g. `husky_changed_files -- '*.rs'`.
+husky_changed_files() {
+    git diff --cached --name-only --diff-filter=ACMR "$@"
+}
+"#;
+const TICKET_HOOK: &str = "prepare-commit-msg";
+// `{pattern}` is replaced by the configured pattern, quoted for the shell. An unborn branch in a
+// fresh repository already has its name, a detached HEAD has none and leaves messages alone.
+const TICKET_HOOK_TEMPLATE: &str = r#"#!/bin/sh
+# Prefixes the commit message with the ticket ID found in the branch name
+case "$2" in
+    merge|squash) exit 0 ;;
+esac
+branch=$(git symbolic-ref --short HEAD 2>/dev/null) || exit 0
+ticket=$(printf '%s
+' "$branch" | grep -oE {pattern} | head -n 1)
+[ -n "$ticket" ] || exit 0
+case "$(head -n 1 "$1")" in
+    "$ticket"*) exit 0 ;;
+esac
+{ printf '%s: ' "$ticket"; cat "$1"; } > "$1.husky" && mv "$1.husky" "$1"
+"#;
+const FMT_HOOK: &str = "pre-commit";
+const DEFAULT_FMT_ARGS: [&str; 3] = ["--all", "--", "--check"];
+// `{args}` is replaced by the configured `cargo fmt` arguments, quoted for the shell
+const FMT_HOOK_TEMPLATE: &str = r#"#!/bin/sh
+# Checks that the code is formatted
+if ! cargo fmt {args}; then
+    echo "husky-rs: code is not formatted, run \`cargo fmt\` and stage the changes" >&2
+    exit 1
+fi
+"#;
+const PROTECT_HOOK: &str = "update";
+// `{refs}` is replaced by the space-separated protected refs. Unlike the receive hooks,
+// `update` gets its input as arguments and nothing on stdin.
+const PROTECT_HOOK_TEMPLATE: &str = r#"#!/bin/sh
+# Refuses deleting or rewinding protected refs.
+# git runs `update` once per pushed ref as: update <refname> <old-oid> <new-oid>
+refname="$1"
+oldrev="$2"
+newrev="$3"
+case "$refname" in
+    {refs}) ;;
+    *) exit 0 ;;
+esac
+zero=$(git hash-object --stdin </dev/null | tr '0-9a-f' '0')
+if [ "$newrev" = "$zero" ]; then
+    echo "husky-rs: deleting $refname is not allowed" >&2
+    exit 1
+fi
+if [ "$oldrev" != "$zero" ] && ! git merge-base --is-ancestor "$oldrev" "$newrev"; then
+    echo "husky-rs: non-fast-forward update of $refname is not allowed" >&2
+    exit 1
+fi
+"#;
+pub(crate) const HUSKY_HEADER: &str = "This hook was set by husky-rs";
+// Identifies installed hooks as ours whatever the rest of the header says
+pub(crate) const HUSKY_MARKER: &str = "husky-rs-managed: true";
+// Starts the header line `header_timestamp` adds
+const TIMESTAMP_PREFIX: &str = "installed:";
+
+// Treats an unset variable, or one set to "0"/"false"/"no"/"off"/"", as disabled
+pub(crate) fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| parse_flag(&value))
+}
+
+fn parse_flag(value: &str) -> bool {
+    !matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "" | "0" | "false" | "no" | "off"
+    )
+}
+
+// Where hooks are installed to, kept apart from `install_hooks` so another repository layout
+// only needs its own resolver
+pub(crate) trait HookTarget {
+    fn resolve(&self) -> Result<ResolvedTarget>;
+}
+
+pub(crate) struct ResolvedTarget {
+    // The repository's metadata directory, e.g. `.git`
+    pub(crate) repo_dir: PathBuf,
+    // Where `.husky` and `Cargo.toml` live
+    pub(crate) project_root: PathBuf,
+    // Where hooks are written
+    pub(crate) hooks_dir: PathBuf,
+}
+
+// Everything after loading the configuration, so a caller can bring its own
+pub(crate) fn install_hooks_with(resolved: ResolvedTarget, mut config: Config) -> Result<()> {
+    let ResolvedTarget {
+        repo_dir: git_dir,
+        project_root,
+        hooks_dir: live_hooks_dir,
+    } = resolved;
+    let manifest = project_root.join("Cargo.toml");
+    // The git directory survives `cargo clean`, so a clean rebuild still finds the bodies it
+    // read before. Without one (e.g. hooks exported to a directory), the build directory will do.
+    config.read_cache = if git_dir != live_hooks_dir {
+        Some(git_dir.join(READ_CACHE_DIR))
+    } else {
+        out_dir().map(|out_dir| out_dir.join(READ_CACHE_DIR))
+    };
+    if config.clean {
+        if let Some(read_cache) = config.read_cache.as_ref().filter(|dir| dir.is_dir()) {
+            fs::remove_dir_all(read_cache)?;
+        }
+        return clean_hooks(&live_hooks_dir);
+    }
+    if config.list {
+        return list_hooks(&live_hooks_dir);
+    }
+    if config.promote && !config.review {
+        warn("`HUSKY_PROMOTE` has no effect without `review = true`");
+    }
+    let git_hooks_dir = match config.review {
+        true => live_hooks_dir.join(REVIEW_DIR),
+        false => live_hooks_dir.clone(),
+    };
+    if cfg!(not(unix)) && config.hook_mode.is_some() {
+        warn("`hook_mode` has no effect on this platform");
+    }
+    for name in &config.extra_hooks {
+        if !VALID_HOOK_NAMES.contains(&name.as_str()) {
+            warn(&format!(
+                "'{}' from `extra_hooks` is not a git hook, git will not run it by itself",
+                name
+            ));
+        }
+    }
+    if config.check_interpreters {
+        // Only watched when checking, so a differing `PATH` between shells doesn't force reruns
+        cargo!("cargo:rerun-if-env-changed=PATH");
+    }
+    let user_hooks_dir = match (&config.hooks_archive, &config.hooks_src) {
+        (Some(archive), _) => {
+            let archive = project_root.join(archive);
+            cargo!("cargo:rerun-if-changed={}", archive.display());
+            unpack_hooks_archive(&archive, &config.extra_hooks)?
+        }
+        (None, Some(hooks_src)) => project_root.join(hooks_src),
+        (None, None) => project_root.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR),
+    };
+    check_distinct_dirs(&user_hooks_dir, &git_hooks_dir)?;
+    log!(
+        LogLevel::Debug,
+        "Using git directory '{}'",
+        git_dir.display()
+    );
+    emit_metadata("git_dir", git_dir.display());
+    emit_metadata("hooks_dir", git_hooks_dir.display());
+    report_symlinked_hooks_dir(&live_hooks_dir);
+
+    // Node husky v9 keeps hooks directly in `.husky`, a common leftover after migrating
+    let husky_dir = project_root.join(HUSKY_DIR);
+    let mut husky_dir_hooks =
+        if config.hooks_src.is_none() && config.hooks_archive.is_none() && husky_dir.is_dir() {
+            timed("reading", || {
+                discover_hooks(&husky_dir, &config.extra_hooks)
+            })?
+        } else {
+            BTreeMap::new()
+        };
+    if !config.husky_dir_hooks {
+        for sources in std::mem::take(&mut husky_dir_hooks).values() {
+            for source in sources {
+                warn(&format!(
+                    "Ignoring '{}', hooks belong in '{}' (or set `husky_dir_hooks = true`)",
+                    source.path.display(),
+                    user_hooks_dir.display()
+                ));
+            }
+        }
+    }
+
+    let multiplexed_hooks =
+        read_multiplexed_hooks(&husky_dir.join(MULTIPLEXED_FILE), &config.extra_hooks)?;
+    let generated_hooks = generated_hooks(&config);
+    if !user_hooks_dir.exists()
+        && generated_hooks.is_empty()
+        && husky_dir_hooks.is_empty()
+        && multiplexed_hooks.is_empty()
+    {
+        log!(
+            LogLevel::Debug,
+            "No hooks directory at '{}', nothing to install",
+            user_hooks_dir.display()
+        );
+        emit_metadata("hooks_installed", 0);
+        return Ok(());
+    }
+
+    if !config.check {
+        create_hooks_dir(&git_hooks_dir)?;
+    }
+
+    // Watch both ends: edited sources, and destinations that vanish (e.g. `.git` re-created).
+    // Hooks unpacked from an archive are rewritten on every run, so only the archive is watched.
+    if config.hooks_archive.is_none() && user_hooks_dir.exists() {
+        cargo!(
+            "cargo:rerun-if-changed={}",
+            watched_path(&user_hooks_dir).display()
+        );
+        if config.check_ignored {
+            let gitignore = project_root.join(".gitignore");
+            if gitignore.is_file() {
+                cargo!("cargo:rerun-if-changed={}", gitignore.display());
+            }
+            check_ignored(&project_root, &user_hooks_dir);
+        }
+    }
+    // Picks up a shared env file, `.disabled` list or sourced helper being edited, added or removed
+    if husky_dir.is_dir() {
+        watch_husky_dir(&watched_path(&husky_dir))?;
+    }
+    cargo!("cargo:rerun-if-changed={}", git_hooks_dir.display());
+    if manifest.is_file() {
+        cargo!("cargo:rerun-if-changed={}", manifest.display());
+    }
+    if let Some(workspace) = workspace_manifest(&manifest).filter(|path| *path != manifest) {
+        cargo!("cargo:rerun-if-changed={}", workspace.display());
+    }
+    let gitattributes = project_root.join(GITATTRIBUTES_FILE);
+    if gitattributes.is_file() {
+        cargo!("cargo:rerun-if-changed={}", gitattributes.display());
+    }
+
+    let mut hooks = if user_hooks_dir.exists() {
+        timed("reading", || {
+            discover_hooks(&user_hooks_dir, &config.extra_hooks)
+        })?
+    } else {
+        BTreeMap::new()
+    };
+    for (name, sources) in husky_dir_hooks.into_iter().chain(multiplexed_hooks) {
+        hooks.entry(name).or_default().extend(sources);
+    }
+    let problems = hooks
+        .iter_mut()
+        .filter_map(|(name, sources)| resolve_ambiguous_sources(name, sources, config.strict).err())
+        .collect();
+    validation_result(problems)?;
+
+    for (name, content) in generated_hooks {
+        if hooks.contains_key(name) {
+            log!(
+                LogLevel::Debug,
+                "Using the project's own '{}' hook instead of the built-in template",
+                name
+            );
+            continue;
+        }
+        let source = write_generated_hook("husky-generated-hooks", name, &content)?;
+        hooks.insert(
+            name.to_string(),
+            vec![HookSource {
+                path: source,
+                label: None,
+            }],
+        );
+    }
+    let missing: Vec<_> = config
+        .required_hooks
+        .iter()
+        .filter(|name| !hooks.contains_key(*name))
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        return Err(HuskyError::MissingRequiredHooks(user_hooks_dir, missing));
+    }
+
+    let mut out_of_sync = Vec::new();
+    if let InstallOutcome::OutOfSync(path) = install_helpers(&git_hooks_dir, &config)? {
+        out_of_sync.push(path);
+    }
+    config.libs = read_libs(&user_hooks_dir.join(LIB_DIR))?;
+    out_of_sync.extend(install_libs(&user_hooks_dir, &git_hooks_dir, &config)?);
+
+    let shared_env = husky_dir.join(ENV_FILE);
+    let disabled_file = husky_dir.join(DISABLED_FILE);
+    let disabled = read_disabled_hooks(&disabled_file, &config.extra_hooks)?;
+    let (mut installed, mut up_to_date, mut kept) = (0, 0, 0);
+    let mut report = Vec::new();
+    let mut problems = Vec::new();
+    let mut dst_dirs = BTreeSet::from([git_hooks_dir.clone()]);
+    for planned in plan_hooks(&hooks, &disabled, &git_hooks_dir, &config) {
+        let name = planned.name;
+        match planned.action {
+            HookAction::Install => {}
+            HookAction::Keep => {
+                log!(
+                    LogLevel::Info,
+                    "Hook '{}' already exists, leaving it untouched",
+                    planned.dst.display()
+                );
+                kept += 1;
+                report.push((name, "kept existing"));
+                continue;
+            }
+            HookAction::SkipDisabled => {
+                log!(
+                    LogLevel::Info,
+                    "Skipping hook '{}', disabled in '{}'",
+                    name,
+                    disabled_file.display()
+                );
+                report.push((name, "skipped, disabled"));
+                continue;
+            }
+            HookAction::SkipServerSide => {
+                log!(
+                    LogLevel::Info,
+                    "Skipping server-side hook '{}', set `server_hooks = true` to install it",
+                    name
+                );
+                report.push((name, "skipped, server-side"));
+                continue;
+            }
+            HookAction::SkipDevOnly => {
+                log!(
+                    LogLevel::Info,
+                    "Skipping hook '{}', not in `dev_hooks` while {} is a dev-dependency",
+                    name,
+                    env!("CARGO_PKG_NAME")
+                );
+                report.push((name, "skipped, dev-dependency"));
+                continue;
+            }
+        }
+        if let Some((_, required)) = MIN_GIT_VERSIONS.iter().find(|(hook, _)| *hook == name) {
+            check_git_version(&format!("Hook '{}'", name), *required);
+        }
+        let env_files = hook_env_files(name, &shared_env, &user_hooks_dir);
+        let (dst_dir, dst_name) = hook_destination(name, planned.sources, &git_hooks_dir, &config);
+        dst_dirs.insert(dst_dir.clone());
+        if config.owner.is_some() {
+            if !config.check {
+                create_hooks_dir(&dst_dir)?;
+            }
+            if let InstallOutcome::OutOfSync(path) =
+                install_dispatcher(name, &git_hooks_dir, &config)?
+            {
+                out_of_sync.push(path);
+            }
+        }
+        let result = if is_opaque_hook(name, planned.sources, &config)? {
+            install_opaque_hook(dst_name, &planned.sources[0].path, &dst_dir, &config)
+        } else {
+            install_hook(
+                dst_name,
+                planned.sources,
+                &env_files,
+                &project_root,
+                &dst_dir,
+                &config,
+            )
+        };
+        let outcome = match result {
+            Ok(outcome) => outcome,
+            // Problems with a hook's sources don't stop the other hooks from being installed
+            Err(
+                error @ (HuskyError::EmptyUserHook(_)
+                | HuskyError::Generator(..)
+                | HuskyError::MissingShebang(_)
+                | HuskyError::SyntaxError(..)
+                | HuskyError::HookTooLarge(..)),
+            ) => {
+                problems.push(error);
+                continue;
+            }
+            // Only strict mode holds the other hooks back for a source nobody can read
+            Err(error @ HuskyError::HookNotReadable(_)) if !config.strict => {
+                warn(&format!("{}, skipping it", error));
+                report.push((name, "skipped, unreadable"));
+                continue;
+            }
+            Err(error @ HuskyError::HookNotReadable(_)) => {
+                problems.push(error);
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        match outcome {
+            InstallOutcome::Installed => {
+                installed += 1;
+                report.push((name, "installed"));
+            }
+            InstallOutcome::UpToDate => {
+                up_to_date += 1;
+                report.push((name, "up to date"));
+            }
+            InstallOutcome::Empty => report.push((name, "skipped, empty")),
+            InstallOutcome::OutOfSync(path) => out_of_sync.push(path),
+        }
+    }
+    if !config.check {
+        for dir in &dst_dirs {
+            prune_payloads(dir)?;
+        }
+    }
+    validation_result(problems)?;
+    if !out_of_sync.is_empty() {
+        return Err(HuskyError::OutOfSync(out_of_sync));
+    }
+
+    log!(
+        LogLevel::Info,
+        "Installed {} hook(s) into '{}', {} already up to date, {} existing left untouched",
+        installed,
+        git_hooks_dir.display(),
+        up_to_date,
+        kept
+    );
+    emit_metadata("hooks_installed", installed + up_to_date);
+    if config.report {
+        write_report(&git_dir, &git_hooks_dir, &report)?;
+    }
+    if config.review && config.promote && !config.check {
+        // Hooks written just now are not the ones that were reviewed
+        if installed > 0 {
+            warn(&format!(
+                "Not promoting hooks, {} changed in '{}' and must be reviewed first",
+                installed,
+                git_hooks_dir.display()
+            ));
+        } else {
+            let promoted = promote_hooks(&git_hooks_dir, &live_hooks_dir)?;
+            log!(
+                LogLevel::Info,
+                "Promoted {} reviewed file(s) from '{}' into '{}'",
+                promoted,
+                git_hooks_dir.display(),
+                live_hooks_dir.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+// Copies everything under the review directory into the live hooks directory, keeping the
+// layout so payloads, helpers and `<hook>.d` fragments are still found relative to each hook
+fn promote_hooks(review_dir: &Path, live_dir: &Path) -> Result<usize> {
+    let mut promoted = 0;
+    for entry in fs::read_dir(review_dir)? {
+        let entry = entry?;
+        let dst = live_dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(long_path(&dst))?;
+            promoted += promote_hooks(&entry.path(), &dst)?;
+        } else {
+            // Keeps the permission bits, so hooks stay executable
+            fs::copy(entry.path(), long_path(&dst)).map_err(|err| write_error(&dst, err))?;
+            promoted += 1;
+        }
+    }
+    Ok(promoted)
+}
+
+// Reading hooks from where they are written would wrap each installed hook in another header
+// on every build, so this is refused before anything is written
+fn check_distinct_dirs(user_hooks_dir: &Path, git_hooks_dir: &Path) -> Result<()> {
+    let same = match (
+        fs::canonicalize(user_hooks_dir),
+        fs::canonicalize(git_hooks_dir),
+    ) {
+        (Ok(src), Ok(dst)) => src == dst,
+        // Not both there yet, e.g. a fresh `.git/hooks`, so only the spelled-out paths compare
+        _ => normalize_path(user_hooks_dir) == normalize_path(git_hooks_dir),
+    };
+    if same {
+        return Err(HuskyError::Config(format!(
+            "hooks would be read from and installed into the same directory '{}'",
+            git_hooks_dir.display()
+        )));
+    }
+    Ok(())
+}
+
+// Only labelled fragments like `pre-commit.fmt.sh` may share a hook. Otherwise, e.g. for
+// `pre-commit` next to `pre-commit.sh`, one whole hook is picked before anything is written:
+// the one without an extension, then the first by path. Strict mode refuses to pick.
+fn resolve_ambiguous_sources(
+    name: &str,
+    sources: &mut Vec<HookSource>,
+    strict: bool,
+) -> Result<()> {
+    if sources.len() < 2 || sources.iter().all(|source| source.label.is_some()) {
+        return Ok(());
+    }
+    let paths: Vec<PathBuf> = sources.iter().map(|source| source.path.clone()).collect();
+    let error = HuskyError::AmbiguousHook(name.to_string(), paths);
+    if strict {
+        return Err(error);
+    }
+    sources.retain(|source| source.label.is_none());
+    sources.sort_by_key(|source| (source.path.extension().is_some(), source.path.clone()));
+    sources.truncate(1);
+    warn(&format!(
+        "{}, using '{}' (set HUSKY_STRICT=1 to make this an error)",
+        error,
+        sources[0].path.display()
+    ));
+    Ok(())
+}
+
+// The directory and file name a hook is installed as
+fn hook_destination<'a>(
+    name: &'a str,
+    sources: &'a [HookSource],
+    git_hooks_dir: &Path,
+    config: &'a Config,
+) -> (PathBuf, &'a str) {
+    match &config.owner {
+        Some(owner) => (
+            git_hooks_dir.join(format!("{}{}", name, FRAGMENTS_DIR_SUFFIX)),
+            owner,
+        ),
+        // Fragments merged into one hook are exported under the first one's name
+        None if config.export => (
+            git_hooks_dir.to_path_buf(),
+            sources
+                .first()
+                .and_then(|source| source.path.file_name())
+                .and_then(|file_name| file_name.to_str())
+                .unwrap_or(name),
+        ),
+        None => (git_hooks_dir.to_path_buf(), name),
+    }
+}
+
+// The same for every owner, so projects sharing the hooks directory don't fight over it
+fn install_dispatcher(name: &str, git_hooks_dir: &Path, config: &Config) -> Result<InstallOutcome> {
+    let dst = git_hooks_dir.join(name);
+    let script = build_dispatcher(name, config);
+    if fs::read_to_string(&dst).is_ok_and(|existing| existing == script) {
+        return Ok(InstallOutcome::UpToDate);
+    }
+    if config.check {
+        log!(LogLevel::Error, "Hook '{}' is out of sync", dst.display());
+        return Ok(InstallOutcome::OutOfSync(dst));
+    }
+    log!(LogLevel::Debug, "Installing dispatcher '{}'", dst.display());
+    retry_transient(config.write_retries, || {
+        write_executable_file(&dst, &script, config.hook_mode)
+    })
+    .map_err(|err| write_error(&dst, err))?;
+    Ok(InstallOutcome::Installed)
+}
+
+// Runs the fragments in name order and, unless `on_error` says to continue, stops at the first
+// failing one
+fn build_dispatcher(name: &str, config: &Config) -> String {
+    let fragments = format!(
+        "\"$(dirname -- \"$0\")/{}{}\"/*",
+        name, FRAGMENTS_DIR_SUFFIX
+    );
+    let mut dispatcher = vec!["#!/bin/sh".to_string()];
+    let run = if STDIN_HOOKS.contains(&name) {
+        dispatcher.extend([
+            "input=$(mktemp) || exit 1".to_string(),
+            "trap 'rm -f \"$input\"' EXIT".to_string(),
+            "cat >\"$input\"".to_string(),
+        ]);
+        "    \"$fragment\" \"$@\" <\"$input\""
+    } else {
+        "    \"$fragment\" \"$@\""
+    };
+    let on_failure = match config.on_error.for_hook(name) {
+        OnError::Abort => "exit $?",
+        OnError::Continue => "echo \"husky-rs: '$fragment' failed with status $?, continuing\" >&2",
+    };
+    dispatcher.extend([
+        format!("for fragment in {}; do", fragments),
+        "    [ -f \"$fragment\" ] && [ -x \"$fragment\" ] || continue".to_string(),
+        format!("{} || {}", run, on_failure),
+        "done".to_string(),
+    ]);
+    let dispatcher_config = Config {
+        header_style: config.header_style,
+        header_version: config.header_version.clone(),
+        source_comment: false,
+        ..Config::default()
+    };
+    build_hook_content(dispatcher, &[], &[], &dispatcher_config)
+}
+
+// Removes every hook carrying the marker, with its payload and the helpers; hooks installed
+// by anything else are left alone
+fn clean_hooks(git_hooks_dir: &Path) -> Result<()> {
+    let hooks = managed_hooks(git_hooks_dir)?;
+    let mut dirs = BTreeSet::new();
+    for (path, _) in &hooks {
+        log!(LogLevel::Debug, "Removing hook '{}'", path.display());
+        fs::remove_file(path)?;
+        dirs.extend(path.parent().map(Path::to_path_buf));
+    }
+    for dir in dirs {
+        prune_payloads(&dir)?;
+        if dir != git_hooks_dir {
+            // Only succeeds once no other owner's fragment is left
+            let _ = fs::remove_dir(&dir);
+        }
+    }
+    for dir in [HELPERS_DIR, LIB_DIR] {
+        let dir = git_hooks_dir.join(dir);
+        if dir.is_dir() {
+            fs::remove_dir_all(&dir)?;
+        }
+    }
+    log!(
+        LogLevel::Info,
+        "Removed {} hook(s) from '{}'",
+        hooks.len(),
+        git_hooks_dir.display()
+    );
+    emit_metadata("hooks_installed", 0);
+    Ok(())
+}
+
+// Reports every hook carrying the marker with the version and sources its header names, as
+// warnings so they show without `-vv` and reach the caller of `install_into`
+fn list_hooks(git_hooks_dir: &Path) -> Result<()> {
+    let hooks = managed_hooks(git_hooks_dir)?;
+    if hooks.is_empty() {
+        warn(&format!(
+            "husky-rs list: no hooks installed in '{}'",
+            git_hooks_dir.display()
+        ));
+    }
+    for (path, content) in &hooks {
+        let name = path.strip_prefix(git_hooks_dir).unwrap_or(path);
+        let (version, sources) = describe_managed_hook(content);
+        let sources = match sources.is_empty() {
+            true => String::new(),
+            false => format!(" from {}", sources.join(", ")),
+        };
+        warn(&format!(
+            "husky-rs list: {} {}{}",
+            name.display(),
+            version.map_or("(unknown version)".to_string(), |version| format!(
+                "v{}",
+                version
+            )),
+            sources
+        ));
+    }
+    Ok(())
+}
+
+// Hooks carrying the marker, in the hooks directory and the owners' `<hook>.d` directories
+fn managed_hooks(git_hooks_dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut hooks = Vec::new();
+    if !git_hooks_dir.is_dir() {
+        return Ok(hooks);
+    }
+    let mut dirs = vec![git_hooks_dir.to_path_buf()];
+    for entry in fs::read_dir(git_hooks_dir)? {
+        let path = entry?.path();
+        let is_fragments_dir = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(FRAGMENTS_DIR_SUFFIX));
+        if path.is_dir() && is_fragments_dir {
+            dirs.push(path);
+        }
+    }
+    for dir in dirs {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            match fs::read_to_string(&path) {
+                Ok(content) if is_managed_hook(&content) => hooks.push((path, content)),
+                _ => {}
+            }
+        }
+    }
+    hooks.sort();
+    Ok(hooks)
+}
+
+// The version and `# source:` lines of a header, in either style
+fn describe_managed_hook(content: &str) -> (Option<String>, Vec<String>) {
+    let mut version = None;
+    let mut sources = Vec::new();
+    for line in content
+        .lines()
+        .skip(1)
+        .take_while(|line| line.starts_with('#'))
+    {
+        let line = line.trim_start_matches('#').trim();
+        if let Some(source) = line.strip_prefix("source:") {
+            sources.push(source.trim().to_string());
+            continue;
+        }
+        version = version.or_else(|| {
+            line.split_whitespace()
+                .filter_map(|word| word.trim_end_matches(':').strip_prefix('v'))
+                .find(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+                .map(str::to_string)
+        });
+    }
+    (version, sources)
+}
+
+// Looks for the marker in the leading comment block only, so a hook merely mentioning it
+// in its body isn't mistaken for ours
+pub(crate) fn is_managed_hook(content: &str) -> bool {
+    content
+        .lines()
+        .skip(1)
+        .take_while(|line| line.starts_with('#'))
+        .any(|line| line.trim_start_matches('#').trim() == HUSKY_MARKER)
+}
+
+// Maps every discovered hook to its destination and what installing would do with it,
+// without touching the filesystem beyond checking whether destinations exist
+fn plan_hooks<'a>(
+    hooks: &'a BTreeMap<String, Vec<HookSource>>,
+    disabled: &[String],
+    git_hooks_dir: &Path,
+    config: &Config,
+) -> Vec<PlannedHook<'a>> {
+    hooks
+        .iter()
+        .map(|(name, sources)| {
+            let (dst_dir, dst_name) = hook_destination(name, sources, git_hooks_dir, config);
+            let dst = dst_dir.join(dst_name);
+            let action = if disabled.contains(name) {
+                HookAction::SkipDisabled
+            } else if !config.server_hooks && SERVER_HOOKS.contains(&name.as_str()) {
+                HookAction::SkipServerSide
+            } else if config
+                .dev_hooks
+                .as_ref()
+                .is_some_and(|dev_hooks| !dev_hooks.contains(name))
+            {
+                HookAction::SkipDevOnly
+            } else if config.only_missing && dst.symlink_metadata().is_ok() {
+                HookAction::Keep
+            } else {
+                HookAction::Install
+            };
+            PlannedHook {
+                name,
+                sources,
+                dst,
+                action,
+            }
+        })
+        .collect()
+}
+
+// A record of the last install that outlives the build log
+fn write_report(git_dir: &Path, git_hooks_dir: &Path, hooks: &[(&str, &str)]) -> Result<()> {
+    let mut report = format!(
+        "husky-rs v{}\ngit dir: {}\nhooks dir: {}\n\nhooks:\n",
+        env!("CARGO_PKG_VERSION"),
+        git_dir.display(),
+        git_hooks_dir.display()
+    );
+    if hooks.is_empty() {
+        report.push_str("  (none)\n");
+    }
+    for (name, status) in hooks {
+        report.push_str(&format!("  {:<20} {}\n", name, status));
+    }
+    let warnings = with_session(|session| session.warnings.clone()).unwrap_or_default();
+    report.push_str("\nwarnings:\n");
+    if warnings.is_empty() {
+        report.push_str("  (none)\n");
+    }
+    for warning in &warnings {
+        report.push_str(&format!("  {}\n", warning));
+    }
+
+    // Outside a build, next to the read cache in the git dir
+    let path = out_dir()
+        .unwrap_or_else(|| git_dir.to_path_buf())
+        .join(REPORT_FILE);
+    fs::write(&path, report)?;
+    log!(
+        LogLevel::Info,
+        "Wrote install report to '{}'",
+        path.display()
+    );
+    Ok(())
+}
+
+// Generated into `OUT_DIR`, so the template goes through the same installation as user hooks
+fn write_generated_hook(subdir: &str, name: &str, content: &str) -> Result<PathBuf> {
+    let dir = scratch_dir()?.join(subdir);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+// Splits `.husky/hooks.sh` into one source per section, written to `OUT_DIR` like the templates
+fn read_multiplexed_hooks(
+    path: &Path,
+    extra_hooks: &[String],
+) -> Result<BTreeMap<String, Vec<HookSource>>> {
+    let mut hooks = BTreeMap::new();
+    if !path.is_file() {
+        return Ok(hooks);
+    }
+    for (name, body) in split_sections(read_file_lines(path)?, path, extra_hooks)? {
+        let content: String = body.iter().map(|line| format!("{}\n", line)).collect();
+        let source = write_generated_hook("husky-multiplexed-hooks", &name, &content)?;
+        hooks.insert(
+            name,
+            vec![HookSource {
+                path: source,
+                label: None,
+            }],
+        );
+    }
+    Ok(hooks)
+}
+
+// Everything before the first section, typically the shebang and shared functions, starts
+// every section's body
+fn split_sections(
+    lines: Vec<String>,
+    path: &Path,
+    extra_hooks: &[String],
+) -> Result<Vec<(String, Vec<String>)>> {
+    let mut preamble = Vec::new();
+    let mut sections: Vec<(String, Vec<String>)> = Vec::new();
+    for line in lines {
+        let Some(name) = line.trim().strip_prefix(SECTION_PREFIX) else {
+            match sections.last_mut() {
+                Some((_, body)) => body.push(line),
+                None => preamble.push(line),
+            }
+            continue;
+        };
+        let name = name.trim();
+        if !is_hook_name(name, extra_hooks) {
+            return Err(HuskyError::Config(format!(
+                "section '{}' in '{}' is not a git hook name",
+                name,
+                path.display()
+            )));
+        }
+        if sections.iter().any(|(existing, _)| existing == name) {
+            return Err(HuskyError::Config(format!(
+                "hook '{}' has more than one section in '{}'",
+                name,
+                path.display()
+            )));
+        }
+        sections.push((name.to_string(), preamble.clone()));
+    }
+    Ok(sections)
+}
+
+// The built-in templates enabled by the configuration, each only used where the project
+// doesn't provide the hook itself
+fn generated_hooks(config: &Config) -> Vec<(&'static str, String)> {
+    let mut hooks = Vec::new();
+    if let Some(pattern) = &config.ticket_pattern {
+        let content = TICKET_HOOK_TEMPLATE.replace("{pattern}", &shell_quote(pattern));
+        hooks.push((TICKET_HOOK, content));
+    }
+    if !config.protected_refs.is_empty() {
+        // Quoted patterns match literally, whatever the ref name contains
+        let refs: Vec<_> = config
+            .protected_refs
+            .iter()
+            .map(|name| shell_quote(name))
+            .collect();
+        let refs = refs.join(" | ");
+        hooks.push((PROTECT_HOOK, PROTECT_HOOK_TEMPLATE.replace("{refs}", &refs)));
+    }
+    if let Some(args) = &config.fmt_check {
+        let args: Vec<_> = args.iter().map(|arg| shell_quote(arg)).collect();
+        hooks.push((
+            FMT_HOOK,
+            FMT_HOOK_TEMPLATE.replace("{args}", &args.join(" ")),
+        ));
+    }
+    hooks
+}
+
+// Leaves plain words alone so generated commands stay readable
+fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_=./:,+@%".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+// Shared scripts in name order, so each hook sources them the same way every time
+fn read_libs(lib_dir: &Path) -> Result<Vec<String>> {
+    if !lib_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut libs = Vec::new();
+    for entry in fs::read_dir(lib_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        match file_name.to_str() {
+            Some(name) if name.ends_with(".sh") && entry.file_type()?.is_file() => {
+                libs.push(name.to_string())
+            }
+            _ => log!(
+                LogLevel::Debug,
+                "Skipping '{}': not a `.sh` file",
+                entry.path().display()
+            ),
+        }
+    }
+    libs.sort();
+    Ok(libs)
+}
+
+// Copies the shared scripts next to the hooks sourcing them, returning those that differ in
+// check mode
+fn install_libs(
+    user_hooks_dir: &Path,
+    git_hooks_dir: &Path,
+    config: &Config,
+) -> Result<Vec<PathBuf>> {
+    let mut out_of_sync = Vec::new();
+    for name in &config.libs {
+        let content = fs::read(user_hooks_dir.join(LIB_DIR).join(name))?;
+        let dst = git_hooks_dir.join(LIB_DIR).join(name);
+        if fs::read(&dst).is_ok_and(|existing| existing == content) {
+            continue;
+        }
+        if config.check {
+            out_of_sync.push(dst);
+            continue;
+        }
+        create_hooks_dir(&git_hooks_dir.join(LIB_DIR))?;
+        log!(
+            LogLevel::Debug,
+            "Installing shared script '{}'",
+            dst.display()
+        );
+        fs::write(long_path(&dst), content).map_err(|err| write_error(&dst, err))?;
+    }
+    Ok(out_of_sync)
+}
+
+// Written only when changed, like the hooks themselves, so reruns settle
+fn install_helpers(git_hooks_dir: &Path, config: &Config) -> Result<InstallOutcome> {
+    let helpers_dir = git_hooks_dir.join(HELPERS_DIR);
+    let helper = helpers_dir.join(CHANGED_FILES_HELPER);
+    if fs::read_to_string(&helper).is_ok_and(|existing| existing == CHANGED_FILES_SCRIPT) {
+        return Ok(InstallOutcome::UpToDate);
+    }
+    if config.check {
+        return Ok(InstallOutcome::OutOfSync(helper));
+    }
+    create_hooks_dir(&helpers_dir)?;
+    log!(LogLevel::Debug, "Installing helper '{}'", helper.display());
+    fs::write(long_path(&helper), CHANGED_FILES_SCRIPT)?;
+    Ok(InstallOutcome::Installed)
+}
+
+// Exposed to dependents' build scripts as `DEP_HUSKY_<KEY>`, and kept in the build output
+pub(crate) fn emit_metadata(key: &str, value: impl std::fmt::Display) {
+    cargo!("cargo::metadata={}={}", key, value);
+}
+
+#[cfg(feature = "archive")]
+fn unpack_hooks_archive(archive: &Path, extra_hooks: &[String]) -> Result<PathBuf> {
+    let dest = scratch_dir()?.join("husky-archive-hooks");
+    extract_hooks_archive(archive, &dest, extra_hooks)?;
+    Ok(dest)
+}
+
+#[cfg(not(feature = "archive"))]
+fn unpack_hooks_archive(archive: &Path, _extra_hooks: &[String]) -> Result<PathBuf> {
+    Err(HuskyError::Config(format!(
+        "reading hooks from '{}' requires the `archive` feature of husky-rs",
+        archive.display()
+    )))
+}
+
+// Unpacks the hook entries of an archive into `dest`. Directories are flattened, so both
+// `pre-commit` and `hooks/pre-commit.sh` are picked up, and no entry can escape `dest`.
+#[cfg(feature = "archive")]
+fn extract_hooks_archive(archive: &Path, dest: &Path, extra_hooks: &[String]) -> Result<()> {
+    let archive_error =
+        |err: &dyn std::fmt::Display| io::Error::other(format!("'{}': {}", archive.display(), err));
+
+    if dest.exists() {
+        fs::remove_dir_all(dest)?;
+    }
+    fs::create_dir_all(dest)?;
+
+    let name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let file = File::open(archive).map_err(|err| archive_error(&err))?;
+    if name.ends_with(".zip") {
+        let mut zip = zip::ZipArchive::new(file).map_err(|err| archive_error(&err))?;
+        for index in 0..zip.len() {
+            let mut entry = zip.by_index(index).map_err(|err| archive_error(&err))?;
+            let path = PathBuf::from(entry.name());
+            if let Some(file_name) = entry
+                .is_file()
+                .then(|| hook_entry_name(&path, extra_hooks))
+                .flatten()
+            {
+                io::copy(&mut entry, &mut File::create(dest.join(file_name))?)?;
+            }
+        }
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        for entry in tar.entries().map_err(|err| archive_error(&err))? {
+            let mut entry = entry.map_err(|err| archive_error(&err))?;
+            let path = entry.path()?.into_owned();
+            if entry.header().entry_type().is_file() {
+                if let Some(file_name) = hook_entry_name(&path, extra_hooks) {
+                    io::copy(&mut entry, &mut File::create(dest.join(file_name))?)?;
+                }
+            }
+        }
+    } else {
+        return Err(HuskyError::Config(format!(
+            "unsupported hooks archive '{}', expected a .tar.gz, .tgz or .zip file",
+            archive.display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "archive")]
+fn hook_entry_name<'a>(path: &'a Path, extra_hooks: &[String]) -> Option<&'a std::ffi::OsStr> {
+    let file_name = path.file_name()?;
+    canonical_hook_name(file_name.to_str()?, extra_hooks).map(|_| file_name)
+}
+
+// Some tooling links `.git/hooks` elsewhere, so hooks land somewhere the user may not expect
+fn report_symlinked_hooks_dir(hooks_dir: &Path) {
+    if !hooks_dir
+        .symlink_metadata()
+        .is_ok_and(|meta| meta.is_symlink())
+    {
+        return;
+    }
+    match fs::canonicalize(hooks_dir) {
+        Ok(resolved) => log!(
+            LogLevel::Info,
+            "'{}' is a symlink, hooks are installed into '{}'",
+            hooks_dir.display(),
+            resolved.display()
+        ),
+        Err(err) => warn(&format!(
+            "'{}' is a symlink that cannot be resolved: {}",
+            hooks_dir.display(),
+            err
+        )),
+    }
+}
+
+// `is_dir` succeeds on a directory we may not list, which would otherwise surface as an
+// opaque IO error further down
+pub(crate) fn check_git_dir_readable(git_dir: &Path) -> Result<()> {
+    match fs::read_dir(git_dir) {
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            Err(HuskyError::GitDirUnreadable(git_dir.to_path_buf(), err))
+        }
+        _ => Ok(()),
+    }
+}
+
+// The git dir of a linked worktree names the one it shares with the main worktree, and with
+// it the hooks, in its `commondir` file
+pub(crate) fn common_git_dir(git_dir: PathBuf) -> Result<PathBuf> {
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => canonicalize_with_context(
+            &git_dir.join(common_dir.trim()),
+            "common git directory of a worktree",
+        ),
+        Err(_) => Ok(git_dir),
+    }
+}
+
+// Walks up from `start_path` to the first `.git`, returning the git dir it is or names, and the
+// work tree holding it, which for a submodule or linked worktree is not the git dir's parent
+pub(crate) fn discover_git_dir(start_path: &Path) -> Result<Option<(PathBuf, PathBuf)>> {
+    // A hook running cargo can start the search inside the git dir itself, where `.git/hooks`
+    // may even be a checkout with a `.git` of its own, so search from the working tree instead
+    let start_path = match start_path
+        .ancestors()
+        .filter(|path| path.file_name().is_some_and(|name| name == ".git"))
+        .last()
+        .and_then(Path::parent)
+    {
+        Some(work_tree) => {
+            log!(
+                LogLevel::Debug,
+                "Starting from '{}' inside a git directory, looking from '{}' instead",
+                start_path.display(),
+                work_tree.display()
+            );
+            work_tree
+        }
+        None => start_path,
+    };
+    for path in start_path.ancestors() {
+        let git_dir = path.join(".git");
+        if git_dir.is_dir() {
+            return Ok(Some((git_dir, path.to_path_buf())));
+        } else if git_dir.is_file() {
+            return Ok(Some((read_git_submodule(&git_dir)?, path.to_path_buf())));
+        }
+    }
+    Ok(None)
+}
+
+fn read_git_submodule(git_file: &Path) -> Result<PathBuf> {
+    let content = fs::read_to_string(git_file)?;
+    let content = content.trim_end_matches(['\n', '\r']);
+    // The file reads `gitdir: <path>`, where the path may be relative to the file itself
+    let git_dir = content.strip_prefix("gitdir:").map_or(content, str::trim);
+    let git_dir = git_file.parent().unwrap_or(Path::new("")).join(git_dir);
+    let context = format!("git directory referenced by '{}'", git_file.display());
+    let git_dir = canonicalize_with_context(&git_dir, &context)?;
+    if !git_dir.is_dir() {
+        return Err(HuskyError::GitDirNotFound(git_dir.display().to_string()));
+    }
+    Ok(git_dir)
+}
+
+// Canonicalizes a path, naming what it was needed for so a failure can be traced back
+fn canonicalize_with_context(path: &Path, context: &str) -> Result<PathBuf> {
+    fs::canonicalize(path)
+        .map_err(|err| HuskyError::PathResolution(path.to_owned(), context.to_string(), err))
+}
+
+#[derive(Debug)]
+struct HookSource {
+    path: PathBuf,
+    // Set for fragments like `pre-commit.fmt.sh`, which are merged in label order
+    label: Option<String>,
+}
+
+// Maps each canonical hook name to the sources providing it, sorted by label then path
+fn discover_hooks(
+    user_hooks_dir: &Path,
+    extra_hooks: &[String],
+) -> Result<BTreeMap<String, Vec<HookSource>>> {
+    let mut hooks: BTreeMap<String, Vec<HookSource>> = BTreeMap::new();
+    let ignored = read_ignore_patterns(&user_hooks_dir.join(IGNORE_FILE))?;
+    for entry in fs::read_dir(user_hooks_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if ARTIFACT_PATTERNS
+            .iter()
+            .any(|pattern| glob_match(pattern, &file_name.to_string_lossy()))
+        {
+            log!(
+                LogLevel::Debug,
+                "Skipping '{}': hidden file or editor artifact",
+                entry.path().display()
+            );
+            continue;
+        }
+        if let Some(pattern) = ignored
+            .iter()
+            .find(|pattern| glob_match(pattern, &file_name.to_string_lossy()))
+        {
+            log!(
+                LogLevel::Debug,
+                "Skipping '{}': matches ignore pattern '{}'",
+                entry.path().display(),
+                pattern
+            );
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        let hook_named = || {
+            file_name
+                .to_str()
+                .is_some_and(|name| canonical_hook_name(name, extra_hooks).is_some())
+        };
+        if file_type.is_dir() && hook_named() {
+            warn(&format!(
+                "Ignoring directory '{}', hooks must be files",
+                entry.path().display()
+            ));
+            continue;
+        }
+        // A FIFO, socket or device, which git can't run and reading could block on
+        if !file_type.is_file() && !file_type.is_dir() && !file_type.is_symlink() && hook_named() {
+            warn(&format!(
+                "Ignoring '{}', hooks must be regular files, not a FIFO, socket or device",
+                entry.path().display()
+            ));
+            continue;
+        }
+        match hook_name(&entry, extra_hooks) {
+            Some((name, label)) => hooks.entry(name).or_default().push(HookSource {
+                path: entry.path(),
+                label,
+            }),
+            None => log!(
+                LogLevel::Debug,
+                "Skipping '{}': not a valid hook file",
+                entry.path().display()
+            ),
+        }
+    }
+    for sources in hooks.values_mut() {
+        sources.sort_by(|a, b| (&a.label, &a.path).cmp(&(&b.label, &b.path)));
+    }
+    Ok(hooks)
+}
+
+// One glob pattern per line, `#` starts a comment
+fn read_ignore_patterns(path: &Path) -> Result<Vec<String>> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    Ok(read_file_lines(path)?
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+// Matches a file name against a pattern where `*` is any run of characters and `?` any one
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of the name it has consumed so far
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn hook_name(entry: &fs::DirEntry, extra_hooks: &[String]) -> Option<(String, Option<String>)> {
+    if !entry.file_type().is_ok_and(|ft| ft.is_file()) {
+        return None;
+    }
+    let file_name = entry.file_name().into_string().ok()?;
+    let (name, label) = canonical_hook_name(&file_name, extra_hooks)?;
+    Some((name.to_string(), label.map(str::to_string)))
+}
+
+// Strips a recognized extension and an optional label, so `pre-commit`, `pre-commit.sh`
+// and `pre-commit.fmt.sh` all map to `pre-commit` (the latter with the label `fmt`)
+fn canonical_hook_name<'a>(
+    file_name: &'a str,
+    extra_hooks: &[String],
+) -> Option<(&'a str, Option<&'a str>)> {
+    if file_name.ends_with(ENV_FILE_EXTENSION) {
+        return None;
+    }
+    let stem = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if HOOK_EXTENSIONS.contains(&extension) => stem,
+        _ => file_name,
+    };
+    let (name, label) = match stem.split_once('.') {
+        Some((name, label)) if !label.is_empty() => (name, Some(label)),
+        _ => (stem, None),
+    };
+    is_hook_name(name, extra_hooks).then_some((name, label))
+}
+
+fn is_hook_name(name: &str, extra_hooks: &[String]) -> bool {
+    VALID_HOOK_NAMES.contains(&name) || extra_hooks.iter().any(|extra| extra == name)
+}
+
+fn read_disabled_hooks(path: &Path, extra_hooks: &[String]) -> Result<Vec<String>> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let mut disabled = Vec::new();
+    for line in read_file_lines(path)? {
+        let name = line.split('#').next().unwrap_or_default().trim();
+        if name.is_empty() {
+            continue;
+        }
+        if !is_hook_name(name, extra_hooks) {
+            warn(&format!(
+                "Ignoring '{}' in '{}', it is not a git hook name",
+                name,
+                path.display()
+            ));
+            continue;
+        }
+        disabled.push(name.to_string());
+    }
+    Ok(disabled)
+}
+
+// The shared env file comes first, so a hook's own env file can override it
+fn hook_env_files(name: &str, shared_env: &Path, user_hooks_dir: &Path) -> Vec<PathBuf> {
+    let own_env = user_hooks_dir.join(format!("{}{}", name, ENV_FILE_EXTENSION));
+    [shared_env.to_path_buf(), own_env]
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+fn empty_hook(path: &Path, config: &Config) -> Result<InstallOutcome> {
+    match config.empty_hook {
+        EmptyHookPolicy::Error => return Err(HuskyError::EmptyUserHook(path.to_path_buf())),
+        EmptyHookPolicy::Warn => warn(&format!(
+            "User hook script is empty, not installing it: '{}'",
+            path.display()
+        )),
+        EmptyHookPolicy::Skip => {
+            log!(LogLevel::Info, "Skipping empty hook '{}'", path.display())
+        }
+    }
+    Ok(InstallOutcome::Empty)
+}
+
+// Checked before reading, so an oversized source is never loaded. One that cannot even be
+// inspected is left to the read, which reports it.
+fn check_hook_size(path: &Path, config: &Config) -> Result<()> {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Ok(());
+    };
+    if metadata.len() > config.max_hook_size {
+        return Err(HuskyError::HookTooLarge(
+            path.to_path_buf(),
+            metadata.len(),
+            config.max_hook_size,
+        ));
+    }
+    Ok(())
+}
+
+fn install_hook(
+    name: &str,
+    sources: &[HookSource],
+    env_files: &[PathBuf],
+    project_root: &Path,
+    dst_dir: &Path,
+    config: &Config,
+) -> Result<InstallOutcome> {
+    let dst = dst_dir.join(name);
+    for source in sources {
+        check_hook_size(&source.path, config)?;
+    }
+    let content = timed("reading", || {
+        read_hook_sources_cached(name, sources, project_root, config)
+    })?;
+    if is_empty_hook(&content) {
+        return empty_hook(&sources[0].path, config);
+    }
+    if config.require_shebang && !content[0].starts_with("#!") {
+        return Err(HuskyError::MissingShebang(sources[0].path.clone()));
+    }
+    if config.check_interpreters {
+        check_interpreter(name, &content[0], &sources[0].path);
+    }
+    if let Some(required) = content
+        .iter()
+        .find_map(|line| line.trim().strip_prefix(REQUIRES_GIT_DIRECTIVE))
+    {
+        match parse_git_version(required) {
+            Some(required) => {
+                check_git_version(&format!("'{}'", sources[0].path.display()), required)
+            }
+            None => warn(&format!(
+                "'{}' declares an unreadable git version '{}', expected e.g. `2.36`",
+                sources[0].path.display(),
+                required.trim()
+            )),
+        }
+    }
+
+    let source_names: Vec<String> = sources
+        .iter()
+        .map(|source| source_name(&source.path, project_root))
+        .collect();
+    let eol = gitattributes_eol(project_root, &source_names[0])?;
+    let (script, payload) = if config.exec_payload {
+        let payload = apply_eol(build_payload(content, config), eol);
+        let payload_path = dst_dir.join(PAYLOAD_DIR).join(content_hash(&payload));
+        let launcher = build_launcher(&payload_path, &source_names, config);
+        (launcher, Some((payload_path, payload)))
+    } else {
+        let script = build_hook_content(content, &source_names, env_files, config);
+        (apply_eol(script, eol), None)
+    };
+    if config.check_syntax {
+        let body = payload.as_ref().map_or(&script, |(_, payload)| payload);
+        check_syntax(body, &sources[0].path)?;
+    }
+
+    // Compare against what is actually on disk, so a missing destination is always reinstalled.
+    // A payload no hook refers to anymore is pruned once all hooks are installed.
+    let payload_current = match &payload {
+        Some((payload_path, payload)) => {
+            fs::read_to_string(payload_path).is_ok_and(|existing| existing == *payload)
+        }
+        None => true,
+    };
+    // A timestamp makes every install differ, so only `HUSKY_CHECK` looks past it
+    let unchanged = |existing: String| match config.header_timestamp {
+        true => config.check && without_timestamp(&existing) == without_timestamp(&script),
+        false => existing == script,
+    };
+    if payload_current && fs::read_to_string(&dst).is_ok_and(unchanged) {
+        // A changed `hook_mode` alone doesn't warrant rewriting the hook
+        if !config.check {
+            set_hook_mode(&dst, config.hook_mode)?;
+        }
+        log!(LogLevel::Debug, "Hook '{}' is up to date", dst.display());
+        return Ok(InstallOutcome::UpToDate);
+    }
+    if config.check {
+        log!(LogLevel::Error, "Hook '{}' is out of sync", dst.display());
+        return Ok(InstallOutcome::OutOfSync(dst));
+    }
+
+    for source in sources {
+        log!(
+            LogLevel::Debug,
+            "Installing hook '{}' to '{}'",
+            source.path.display(),
+            dst.display()
+        );
+    }
+    if let Some((payload_path, payload)) = &payload {
+        create_hooks_dir(&dst_dir.join(PAYLOAD_DIR))?;
+        timed("writing", || {
+            retry_transient(config.write_retries, || {
+                write_executable_file(payload_path, payload, config.hook_mode)
+            })
+        })
+        .map_err(|err| write_error(payload_path, err))?;
+    }
+    timed("writing", || {
+        retry_transient(config.write_retries, || {
+            write_executable_file(&dst, &script, config.hook_mode)
+        })
+    })
+    .map_err(|err| write_error(&dst, err))?;
+    Ok(InstallOutcome::Installed)
+}
+
+// Listed in `opaque_hooks`, installed with `raw`, or not text at all, e.g. a compiled binary
+// that reading it line by line would corrupt
+fn is_opaque_hook(name: &str, sources: &[HookSource], config: &Config) -> Result<bool> {
+    let listed = config.raw || config.opaque_hooks.iter().any(|hook| hook == name);
+    if sources.len() > 1 {
+        if listed {
+            let setting = if config.raw { "raw" } else { "opaque_hooks" };
+            return Err(HuskyError::Config(format!(
+                "`{}` copies '{}' as is, but it has several sources to combine",
+                setting, name
+            )));
+        }
+        return Ok(false);
+    }
+    if listed {
+        return Ok(true);
+    }
+    // Not read just to tell, `install_hook` reports it
+    if check_hook_size(&sources[0].path, config).is_err() {
+        return Ok(false);
+    }
+    let content = fs::read(&sources[0].path)
+        .map_err(|err| source_read_error(&sources[0].path, err.into()))?;
+    Ok(content.contains(&0) || std::str::from_utf8(&content).is_err())
+}
+
+// Copies the source as is: no header, so `HUSKY_CLEAN` and `HUSKY_LIST` don't know the hook
+fn install_opaque_hook(
+    name: &str,
+    source: &Path,
+    dst_dir: &Path,
+    config: &Config,
+) -> Result<InstallOutcome> {
+    let dst = dst_dir.join(name);
+    check_hook_size(source, config)?;
+    let content = timed("reading", || fs::read(source))
+        .map_err(|err| source_read_error(source, err.into()))?;
+    let lines: Vec<_> = String::from_utf8_lossy(&content)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    if is_empty_hook(&lines) {
+        return empty_hook(source, config);
+    }
+    if fs::read(&dst).is_ok_and(|existing| existing == content) {
+        if !config.check {
+            set_hook_mode(&dst, config.hook_mode)?;
+        }
+        log!(LogLevel::Debug, "Hook '{}' is up to date", dst.display());
+        return Ok(InstallOutcome::UpToDate);
+    }
+    if config.check {
+        log!(LogLevel::Error, "Hook '{}' is out of sync", dst.display());
+        return Ok(InstallOutcome::OutOfSync(dst));
+    }
+
+    log!(
+        LogLevel::Debug,
+        "Copying hook '{}' to '{}' as is",
+        source.display(),
+        dst.display()
+    );
+    timed("writing", || {
+        retry_transient(config.write_retries, || {
+            write_executable_file_with(&dst, config.hook_mode, |file| file.write_all(&content))
+        })
+    })
+    .map_err(|err| write_error(&dst, err))?;
+    Ok(InstallOutcome::Installed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Eol {
+    Lf,
+    Crlf,
+}
+
+// The `eol` attribute the project's root `.gitattributes` sets for a hook's source, given
+// relative to the project root. Patterns without a `/` match the file name, others the whole
+// path, and the last matching line wins like in git.
+fn gitattributes_eol(project_root: &Path, source: &str) -> Result<Option<Eol>> {
+    let path = project_root.join(GITATTRIBUTES_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let file_name = source.rsplit('/').next().unwrap_or(source);
+    let mut eol = None;
+    for line in read_file_lines(&path)? {
+        let mut fields = line.split_whitespace();
+        let Some(pattern) = fields.next().filter(|pattern| !pattern.starts_with('#')) else {
+            continue;
+        };
+        let matches = match pattern.strip_prefix('/') {
+            Some(anchored) => glob_match(anchored, source),
+            None if pattern.contains('/') => glob_match(pattern, source),
+            None => glob_match(pattern, file_name),
+        };
+        if !matches {
+            continue;
+        }
+        for attribute in fields {
+            match attribute {
+                "eol=lf" => eol = Some(Eol::Lf),
+                "eol=crlf" => eol = Some(Eol::Crlf),
+                "-eol" | "!eol" => eol = None,
+                _ => {}
+            }
+        }
+    }
+    Ok(eol)
+}
+
+// Shell hooks always keep LF, which is all `sh` understands, as does the shebang line of
+// any hook, which the kernel would otherwise read as naming `python3\r`
+fn apply_eol(content: String, eol: Option<Eol>) -> String {
+    if eol != Some(Eol::Crlf) {
+        return content;
+    }
+    let Some((shebang, body)) = content.split_once('\n') else {
+        return content;
+    };
+    if is_shell(shebang) {
+        return content;
+    }
+    format!("{}\n{}", shebang, body.replace('\n', "\r\n"))
+}
+
+// A full disk is reported on its own, as it calls for freeing space rather than fixing husky-rs
+fn write_error(path: &Path, err: io::Error) -> HuskyError {
+    // ENOSPC, and ERROR_HANDLE_DISK_FULL and ERROR_DISK_FULL
+    #[cfg(not(windows))]
+    const DISK_FULL_ERRORS: [i32; 1] = [28];
+    #[cfg(windows)]
+    const DISK_FULL_ERRORS: [i32; 2] = [39, 112];
+    match err.raw_os_error() {
+        Some(code) if DISK_FULL_ERRORS.contains(&code) => HuskyError::DiskFull(path.to_path_buf()),
+        _ => HuskyError::Io(err),
+    }
+}
+
+// A tiny hook handing over to the payload, which keeps the hook's arguments and stdin
+fn build_launcher(payload_path: &Path, sources: &[String], config: &Config) -> String {
+    let hash = payload_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let launcher = vec![
+        "#!/bin/sh".to_string(),
+        format!(
+            "exec \"$(dirname -- \"$0\")/{}/{}\" \"$@\"",
+            PAYLOAD_DIR, hash
+        ),
+    ];
+    // Env files, strict mode and footers would be lost on `exec`, they only apply inline
+    let launcher_config = Config {
+        header_style: config.header_style,
+        header_version: config.header_version.clone(),
+        header_width: config.header_width,
+        header_timestamp: config.header_timestamp,
+        source_comment: config.source_comment,
+        ..Config::default()
+    };
+    build_hook_content(launcher, sources, &[], &launcher_config)
+}
+
+// The user's script unchanged, given a shebang if it has none so it can be executed directly
+fn build_payload(mut content: Vec<String>, config: &Config) -> String {
+    while content.last().is_some_and(|line| line.trim().is_empty()) {
+        content.pop();
+    }
+    if !content.first().is_some_and(|line| line.starts_with("#!")) {
+        content.insert(0, fallback_shebang(config).to_string());
+    }
+    content.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+// FNV-1a, which is stable across Rust versions unlike `DefaultHasher`. Payloads are compared
+// in full before being reused, so a collision costs a rewrite, never a wrong hook.
+fn content_hash(content: &str) -> String {
+    let hash = content
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
+// The payload a launcher execs, by its hash
+fn payload_reference(content: &str) -> Option<&str> {
+    let prefix = format!("/{}/", PAYLOAD_DIR);
+    content
+        .lines()
+        .filter(|line| line.starts_with("exec "))
+        .find_map(|line| line.split_once(&prefix))
+        .and_then(|(_, rest)| rest.split('"').next())
+}
+
+// Removes payloads none of the hooks in `hooks_dir` refers to anymore, e.g. after a hook was
+// installed inline again or its body changed, and the payload directory once it's empty
+fn prune_payloads(hooks_dir: &Path) -> Result<()> {
+    let payload_dir = hooks_dir.join(PAYLOAD_DIR);
+    if !payload_dir.is_dir() {
+        return Ok(());
+    }
+    let mut referenced = BTreeSet::new();
+    for entry in fs::read_dir(hooks_dir)? {
+        let path = entry?.path();
+        if let Ok(content) = fs::read_to_string(&path) {
+            referenced.extend(payload_reference(&content).map(str::to_string));
+        }
+    }
+    for entry in fs::read_dir(&payload_dir)? {
+        let entry = entry?;
+        if !referenced.contains(entry.file_name().to_string_lossy().as_ref()) {
+            log!(
+                LogLevel::Debug,
+                "Removing unused payload '{}'",
+                entry.path().display()
+            );
+            fs::remove_file(entry.path())?;
+        }
+    }
+    // Only succeeds once the last payload is gone
+    let _ = fs::remove_dir(&payload_dir);
+    Ok(())
+}
+
+// Relative to the project root with `/` separators, so headers read the same on every platform
+// Relative to the project, or for generated hooks to the scratch directory, which outside a build
+// is a new one for every install
+fn source_name(path: &Path, project_root: &Path) -> String {
+    let scratch_dir = with_session(|session| session.out_dir.clone().or(session.temp_dir.clone()));
+    let relative = path
+        .strip_prefix(project_root)
+        .ok()
+        .or_else(|| path.strip_prefix(scratch_dir.flatten()?).ok())
+        .unwrap_or(path);
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Concatenates the sources in order, keeping only the first one's shebang
+fn read_hook_sources(
+    sources: &[HookSource],
+    project_root: &Path,
+    config: &Config,
+) -> Result<Vec<String>> {
+    #[cfg(test)]
+    tests::SOURCE_READS.with(|reads| reads.set(reads.get() + 1));
+    let mut content = Vec::new();
+    for (index, source) in sources.iter().enumerate() {
+        let mut lines =
+            read_file_lines(&source.path).map_err(|err| source_read_error(&source.path, err))?;
+        if let Some(command) = generator_command(&lines) {
+            if config.generators {
+                lines = run_generator(&command, &source.path, project_root)?;
+            } else {
+                warn(&format!(
+                    "'{}' names a generator, set `generators = true` to run it",
+                    source.path.display()
+                ));
+            }
+        }
+        let skip_shebang = index > 0 && lines.first().is_some_and(|line| line.starts_with("#!"));
+        content.extend(lines.into_iter().skip(usize::from(skip_shebang)));
+    }
+    Ok(content)
+}
+
+// Reuses the body read by an earlier build while its sources keep their size, modification time
+// and file, so a rerun for anything else, like a new husky-rs version in the header, only
+// rebuilds the header. Without a directory to keep bodies in, sources are read every time.
+fn read_hook_sources_cached(
+    name: &str,
+    sources: &[HookSource],
+    project_root: &Path,
+    config: &Config,
+) -> Result<Vec<String>> {
+    let (Some(read_cache), Some(fingerprint)) =
+        (&config.read_cache, sources_fingerprint(sources, config))
+    else {
+        return read_hook_sources(sources, project_root, config);
+    };
+    // By the sources rather than the name, which is the same for every hook of an `owner`
+    let paths: String = sources
+        .iter()
+        .map(|source| format!("{}\n", source.path.display()))
+        .collect();
+    let cache_path = read_cache.join(content_hash(&paths));
+    if let Some(body) = fs::read_to_string(&cache_path)
+        .ok()
+        .as_deref()
+        .and_then(|cached| cached.strip_prefix(&fingerprint))
+    {
+        log!(
+            LogLevel::Debug,
+            "Reusing the body of hook '{}' from '{}'",
+            name,
+            cache_path.display()
+        );
+        return Ok(body.split_terminator('\n').map(str::to_string).collect());
+    }
+    let content = read_hook_sources(sources, project_root, config)?;
+    let cached: String = content.iter().map(|line| format!("{}\n", line)).collect();
+    // Only ever saves a read, so failing to keep it is no reason to fail the install
+    if let Err(err) = fs::create_dir_all(cache_path.parent().unwrap_or(Path::new("")))
+        .and_then(|()| fs::write(&cache_path, fingerprint + &cached))
+    {
+        log!(
+            LogLevel::Debug,
+            "Could not cache the body of hook '{}': {}",
+            name,
+            err
+        );
+    }
+    Ok(content)
+}
+
+// Identifies the state of a hook's sources without reading them, as a first line for the cache.
+// On Unix, a source replaced by another file or made unreadable counts as changed too. A
+// generator's output can change while its source doesn't, so those hooks are never cached.
+fn sources_fingerprint(sources: &[HookSource], config: &Config) -> Option<String> {
+    if config.generators {
+        return None;
+    }
+    let mut fingerprint = String::new();
+    for source in sources {
+        let metadata = fs::metadata(&source.path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?;
+        fingerprint.push_str(&format!(
+            "{}:{}:{}:{}|",
+            source.path.display(),
+            metadata.len(),
+            modified.as_nanos(),
+            file_identity(&metadata)
+        ));
+    }
+    fingerprint.push('\n');
+    Some(fingerprint)
+}
+
+#[cfg(unix)]
+fn file_identity(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    format!(
+        "{}:{}:{:o}",
+        metadata.dev(),
+        metadata.ino(),
+        metadata.mode()
+    )
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &fs::Metadata) -> String {
+    String::new()
+}
+
+fn is_empty_hook(content: &[String]) -> bool {
+    content
+        .iter()
+        .all(|line| line.trim().is_empty() || line.starts_with("#!"))
+}
+
+// The command of a `# husky:generate: <command>` line, which may follow a shebang
+fn generator_command(lines: &[String]) -> Option<String> {
+    lines
+        .iter()
+        .find(|line| !line.starts_with("#!"))
+        .and_then(|line| line.trim().strip_prefix(GENERATE_DIRECTIVE))
+        .map(|command| command.trim().to_string())
+}
+
+// Runs through `sh` from the project root, like the hook itself would be run by git
+fn run_generator(command: &str, source: &Path, project_root: &Path) -> Result<Vec<String>> {
+    log!(
+        LogLevel::Debug,
+        "Generating hook '{}' with `{}`",
+        source.display(),
+        command
+    );
+    let output = std::process::Command::new("sh")
+        .args(["-c", command])
+        .current_dir(project_root)
+        .output()
+        .map_err(|err| {
+            HuskyError::Generator(source.to_path_buf(), format!("`{}`: {}", command, err))
+        })?;
+    if !output.status.success() {
+        return Err(HuskyError::Generator(
+            source.to_path_buf(),
+            format!(
+                "`{}` exited with {}: {}",
+                command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+    let stdout = String::from_utf8(output.stdout).map_err(|_| {
+        HuskyError::Generator(
+            source.to_path_buf(),
+            format!("`{}` printed invalid UTF-8", command),
+        )
+    })?;
+    Ok(trim_blank_lines(
+        stdout.lines().map(str::to_string).collect(),
+    ))
+}
+
+// Names the hook source a permission problem is about, rather than failing with a bare IO error
+fn source_read_error(path: &Path, error: HuskyError) -> HuskyError {
+    match error {
+        HuskyError::Io(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            HuskyError::HookNotReadable(path.to_path_buf())
+        }
+        error => error,
+    }
+}
+
+fn read_file_lines(path: &Path) -> Result<Vec<String>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    let lines: Vec<String> = reader.lines().collect::<io::Result<_>>()?;
+    Ok(trim_blank_lines(lines))
+}
+
+fn trim_blank_lines(mut lines: Vec<String>) -> Vec<String> {
+    // Remove leading empty lines
+    while lines.first().is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(0);
+    }
+
+    // Remove trailing empty lines
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    // Ensure the last line is empty if no other lines exist
+    if lines.is_empty() || !lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.push(String::new());
+    }
+
+    lines
+}
+
+// The exact content installed for a hook, computed without touching the filesystem
+fn build_hook_content(
+    source_lines: Vec<String>,
+    sources: &[String],
+    env_files: &[PathBuf],
+    config: &Config,
+) -> String {
+    generate_husky_hook_script(source_lines, sources, env_files, config)
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+fn generate_husky_hook_script(
+    mut content: Vec<String>,
+    sources: &[String],
+    env_files: &[PathBuf],
+    config: &Config,
+) -> Vec<String> {
+    let shebang = content
+        .first()
+        .filter(|line| is_shebang(line))
+        .map(|line| line.trim().to_string())
+        .unwrap_or_else(|| fallback_shebang(config).to_string());
+    let shebang = match &config.canonical_shebang {
+        Some(canonical) if is_shell(&shebang) || config.force_canonical_shebang => {
+            canonical.clone()
+        }
+        _ => shebang,
+    };
+
+    let comment_block: Vec<String> = if config.preserve_comments {
+        let has_shebang = content.first().is_some_and(|line| is_shebang(line));
+        content.drain(..usize::from(has_shebang));
+        // Leading comments with the blank lines around them, up to the first command
+        let end = content
+            .iter()
+            .position(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .unwrap_or(content.len());
+        content.drain(..end).collect()
+    } else {
+        content = content
+            .into_iter()
+            .skip_while(|line| is_shebang(line) || line.trim().is_empty())
+            .collect();
+        Vec::new()
+    };
+
+    // Only the written line changes, the rest still goes by the interpreter the hook asked for
+    let first_line = match &config.git_bash {
+        Some(bash) if matches!(interpreter(&shebang), "sh" | "bash") => {
+            format!("#!{}", bash.display().to_string().replace('\\', "/"))
+        }
+        _ => shebang.clone(),
+    };
+    let version = config.header_version.text();
+    let installed = match config.header_timestamp {
+        true => format!("# {} {}\n", TIMESTAMP_PREFIX, rfc3339_now()),
+        false => String::new(),
+    };
+    let header = match config.header_style {
+        HeaderStyle::Minimal => format!(
+            "{}\n# {}{}\n{}# {}",
+            first_line,
+            HUSKY_HEADER,
+            version.map_or(String::new(), |version| format!(" v{}", version)),
+            installed,
+            HUSKY_MARKER
+        ),
+        HeaderStyle::Full => {
+            let source_lines: String = sources
+                .iter()
+                .filter(|_| config.source_comment)
+                .map(|source| format!("# source: {}\n", source))
+                .collect();
+            format!(
+                "{}
+#
+# {}
+# {}{}
+{}{}# {}
+#
+",
+                first_line,
+                HUSKY_HEADER,
+                version.map_or(String::new(), |version| format!("v{}: ", version)),
+                env!("CARGO_PKG_HOMEPAGE"),
+                source_lines,
+                installed,
+                HUSKY_MARKER
+            )
+        }
+    };
+
+    let header = match config.header_width {
+        Some(width) => wrap_header(&header, width, sources),
+        None => header,
+    };
+
+    let mut result = vec![header];
+    result.extend(comment_block);
+    if is_shell(&shebang) {
+        // Fragments live one level below the helpers, in `<hook>.d`
+        let hooks_dir = match config.owner {
+            Some(_) => "$(dirname -- \"$0\")/..",
+            None => "$(dirname -- \"$0\")",
+        };
+        // Only hooks calling the helper source it, the others don't depend on `_/` at all
+        if content
+            .iter()
+            .any(|line| line.contains(CHANGED_FILES_FUNCTION))
+        {
+            result.push(format!(
+                ". \"{}/{}/{}\"",
+                hooks_dir, HELPERS_DIR, CHANGED_FILES_HELPER
+            ));
+        }
+        result.extend(
+            config
+                .libs
+                .iter()
+                .map(|lib| format!(". \"{}/{}/{}\"", hooks_dir, LIB_DIR, lib)),
+        );
+        result.extend(
+            env_files
+                .iter()
+                .map(|path| format!(". {}", shell_quote(&path.display().to_string()))),
+        );
+    }
+    if config.strict_body {
+        result.extend(strict_mode_line(&shebang).map(str::to_string));
+    }
+    match &config.footer {
+        // A trap also fires after `set -e` aborts the body, and keeps its exit status
+        Some(footer) if config.footer_on_exit && is_shell(&shebang) => {
+            result.push("husky_footer() {".to_string());
+            result.extend(footer.lines().map(str::to_string));
+            result.push("}".to_string());
+            result.push("trap husky_footer EXIT".to_string());
+            result.extend(content);
+        }
+        Some(footer) => {
+            while content.last().is_some_and(|line| line.trim().is_empty()) {
+                content.pop();
+            }
+            result.extend(content);
+            result.extend(footer.lines().map(str::to_string));
+            result.push(String::new());
+        }
+        None => result.extend(content),
+    }
+    result
+}
+
+// Git for Windows runs hooks through the first `sh`/`bash` on `PATH`, which isn't always
+// its own; its bash lives in `bin` of the installation, next to `cmd\git.exe`
+#[cfg(windows)]
+fn find_git_bash() -> Option<PathBuf> {
+    let bash = std::process::Command::new("where")
+        .arg("git")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .flat_map(|git| {
+                    let git = PathBuf::from(git.trim());
+                    git.ancestors()
+                        .skip(1)
+                        .take(3)
+                        .map(|dir| dir.join("bin").join("bash.exe"))
+                        .collect::<Vec<_>>()
+                })
+                .find(|bash| bash.is_file())
+        });
+    if bash.is_none() {
+        warn("`git_bash` is set, but Git for Windows' bash.exe was not found");
+    }
+    bash
+}
+
+#[cfg(not(windows))]
+fn find_git_bash() -> Option<PathBuf> {
+    warn("`git_bash` has no effect on this platform");
+    None
+}
+
+fn fallback_shebang(config: &Config) -> &str {
+    config
+        .default_shebang
+        .as_deref()
+        .unwrap_or("#!/usr/bin/env bash")
+}
+
+// The most portable shell the host has: `/bin/sh` where it exists, bash otherwise
+fn host_default_shebang() -> String {
+    if cfg!(unix) && Path::new("/bin/sh").exists() {
+        "#!/bin/sh".to_string()
+    } else {
+        "#!/usr/bin/env bash".to_string()
+    }
+}
+
+// Wraps the header's comment lines at `width` columns between words, warning about any line
+// still too long. The shebang, marker and source lines are parsed back, so they stay whole.
+fn wrap_header(header: &str, width: usize, sources: &[String]) -> String {
+    let too_long = |line: &str| line.chars().count() > width;
+    let mut lines = Vec::new();
+    for (index, line) in header.split('\n').enumerate() {
+        let text = line.strip_prefix("# ").unwrap_or("");
+        if index == 0 || !too_long(line) || text == HUSKY_MARKER || text.starts_with("source:") {
+            lines.push(line.to_string());
+            continue;
+        }
+        let mut wrapped = String::from("#");
+        for word in text.split_whitespace() {
+            if wrapped.len() > 1 && too_long(&format!("{} {}", wrapped, word)) {
+                lines.push(std::mem::replace(&mut wrapped, String::from("#")));
+            }
+            wrapped.push(' ');
+            wrapped.push_str(word);
+        }
+        lines.push(wrapped);
+    }
+    if let Some(line) = lines.iter().skip(1).find(|line| too_long(line)) {
+        warn(&format!(
+            "The header of '{}' has a line of {} characters, over `header_width = {}`: {}",
+            sources.first().map_or("a generated hook", String::as_str),
+            line.chars().count(),
+            width,
+            line
+        ));
+    }
+    lines.join("\n")
+}
+
+// The hook without the header line added by `header_timestamp`
+fn without_timestamp(content: &str) -> String {
+    let prefix = format!("# {} ", TIMESTAMP_PREFIX);
+    let header_len = content
+        .lines()
+        .skip(1)
+        .take_while(|line| line.starts_with('#'))
+        .count();
+    let mut skipped = false;
+    content
+        .split_inclusive('\n')
+        .enumerate()
+        .filter(|&(index, line)| {
+            let timestamp = !skipped && index <= header_len && line.starts_with(&prefix);
+            skipped |= timestamp;
+            !timestamp
+        })
+        .map(|(_, line)| line)
+        .collect()
+}
+
+fn rfc3339_now() -> String {
+    rfc3339(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+    )
+}
+
+// Seconds since the Unix epoch as an RFC 3339 time in UTC, e.g. `2024-05-01T09:30:00Z`
+fn rfc3339(secs: u64) -> String {
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    // Howard Hinnant's `civil_from_days`, shifted so years start in March
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+// `pipefail` is not POSIX, so plain `sh` only gets `-eu`; other interpreters are left alone
+fn strict_mode_line(shebang: &str) -> Option<&'static str> {
+    match interpreter(shebang) {
+        "bash" | "zsh" | "ksh" => Some("set -euo pipefail"),
+        "sh" | "dash" => Some("set -eu"),
+        _ => None,
+    }
+}
+
+fn is_shell(shebang: &str) -> bool {
+    matches!(interpreter(shebang), "sh" | "dash" | "bash" | "zsh" | "ksh")
+}
+
+// Any `#!` line naming a program is kept as written, be it `#!/usr/bin/env bash`, `#!/bin/bash`
+// or `#!/usr/bin/python3`, so the hook runs with exactly the interpreter it asks for
+fn is_shebang(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("#!") && !interpreter(line).is_empty()
+}
+
+// Names the program a shebang runs, e.g. `bash` for both `#!/bin/bash` and `#!/usr/bin/env bash`
+fn interpreter(shebang: &str) -> &str {
+    let mut parts = shebang.trim_start_matches("#!").split_whitespace();
+    let program = parts.next().unwrap_or("");
+    let program = if program.ends_with("/env") {
+        parts.next().unwrap_or("")
+    } else {
+        program
+    };
+    program.rsplit('/').next().unwrap_or(program)
+}
+
+// Reads a shell hook with its own shell's `-n`, which parses without running anything. Other
+// interpreters have no common equivalent and are left alone.
+fn check_syntax(script: &str, source: &Path) -> Result<()> {
+    let shebang = script.lines().next().unwrap_or("");
+    if !is_shell(shebang) {
+        return Ok(());
+    }
+    let shell = interpreter(shebang);
+    let child = std::process::Command::new(shell)
+        .arg("-n")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            warn(&format!(
+                "`{}` not found, cannot check the syntax of '{}'",
+                shell,
+                source.display()
+            ));
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // A shell giving up early closes its end, which its exit status reports better
+        let _ = stdin.write_all(script.as_bytes());
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(HuskyError::SyntaxError(
+            source.to_path_buf(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Warns when `what`, a hook or its source, needs a newer git than the one installed. Without
+// git on `PATH` there is nothing to compare against.
+fn check_git_version(what: &str, required: (u32, u32)) {
+    if let Some(message) = git_version_warning(what, required, installed_git_version()) {
+        warn(&message);
+    }
+}
+
+fn git_version_warning(
+    what: &str,
+    required: (u32, u32),
+    installed: Option<(u32, u32)>,
+) -> Option<String> {
+    let installed = installed.filter(|&installed| installed < required)?;
+    Some(format!(
+        "{} needs git >= {}.{}, but git {}.{} is installed and will not run it as intended",
+        what, required.0, required.1, installed.0, installed.1
+    ))
+}
+
+static GIT_VERSION: OnceLock<Option<(u32, u32)>> = OnceLock::new();
+
+// Only asked once, and only when some hook has a requirement
+fn installed_git_version() -> Option<(u32, u32)> {
+    *GIT_VERSION.get_or_init(|| {
+        let output = std::process::Command::new("git")
+            .arg("--version")
+            .output()
+            .ok()?;
+        parse_git_version(&String::from_utf8_lossy(&output.stdout))
+    })
+}
+
+// The major and minor version in `git version 2.39.3 (Apple Git-146)`, or just `2.39`
+fn parse_git_version(text: &str) -> Option<(u32, u32)> {
+    let version = text
+        .split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |minor| minor.parse().ok())?;
+    Some((major, minor))
+}
+
+// Cargo may not notice edits made behind a symlink, e.g. a `.husky` linked to a shared
+// location, so a path through one is watched at its real location
+fn watched_path(path: &Path) -> PathBuf {
+    let through_symlink = path
+        .ancestors()
+        .any(|dir| dir.symlink_metadata().is_ok_and(|meta| meta.is_symlink()));
+    match through_symlink {
+        true => fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        false => path.to_path_buf(),
+    }
+}
+
+// Watches every file under `.husky`, and each directory so added or removed files count too.
+// Cargo watches a directory with everything inside, so one holding hooks staged by an earlier
+// build isn't watched as a whole, only the files next to them.
+fn watch_husky_dir(dir: &Path) -> Result<()> {
+    let mut holds_staged_hooks = false;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type()?.is_dir() {
+            cargo!("cargo:rerun-if-changed={}", path.display());
+        } else if entry.file_name() == STAGED_HOOKS_DIR {
+            holds_staged_hooks = true;
+        } else {
+            watch_husky_dir(&path)?;
+        }
+    }
+    if !holds_staged_hooks {
+        cargo!("cargo:rerun-if-changed={}", dir.display());
+    }
+    Ok(())
+}
+
+// Asks git itself, which knows every ignore file and global exclude. Without git, or outside a
+// repository, there is nothing to share the hooks through anyway.
+fn check_ignored(project_root: &Path, hooks_dir: &Path) {
+    let output = std::process::Command::new("git")
+        .args(["check-ignore", "--quiet", "--"])
+        .arg(hooks_dir)
+        .current_dir(project_root)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => warn(&format!(
+            "'{}' is ignored by git, so its hooks won't be shared with anyone cloning the repository",
+            hooks_dir.display()
+        )),
+        Ok(_) => {}
+        Err(err) => log!(
+            LogLevel::Debug,
+            "Could not run git to check whether hooks are ignored: {}",
+            err
+        ),
+    }
+}
+
+// Catches hooks that would only fail once git runs them, e.g. `python3` missing from `PATH`
+fn check_interpreter(name: &str, first_line: &str, source: &Path) {
+    if !first_line.starts_with("#!") || interpreter_available(first_line.trim()) {
+        return;
+    }
+    warn(&format!(
+        "Hook '{}' runs with `{}`, which was not found on PATH; install it or fix the shebang in '{}'",
+        name,
+        interpreter(first_line),
+        source.display()
+    ));
+}
+
+fn interpreter_available(shebang: &str) -> bool {
+    let program = shebang
+        .trim_start_matches("#!")
+        .split_whitespace()
+        .next()
+        .unwrap_or("");
+    if !program.ends_with("/env") {
+        return Path::new(program).is_file();
+    }
+    let name = interpreter(shebang);
+    env::var_os("PATH").is_some_and(|path| {
+        env::split_paths(&path).any(|dir| {
+            dir.join(name).is_file()
+                || dir
+                    .join(format!("{}{}", name, env::consts::EXE_SUFFIX))
+                    .is_file()
+        })
+    })
+}
+
+// What an install reports besides its outcome. The build script prints cargo's directives and
+// warnings as they come, while `install_into` prints nothing and hands the warnings back.
+pub(crate) struct Session {
+    // Whether cargo reads the output, as it only does the build script's
+    cargo: bool,
+    // The build script's `OUT_DIR`, for files meant to outlive the install
+    out_dir: Option<PathBuf>,
+    // For scratch files without an `OUT_DIR`, created when first needed and removed with the
+    // session
+    temp_dir: Option<PathBuf>,
+    warnings: Vec<String>,
+    timings: Vec<(&'static str, Duration)>,
+}
+
+thread_local! {
+    static SESSION: RefCell<Option<Session>> = const { RefCell::new(None) };
+}
+
+impl Session {
+    pub(crate) fn new(cargo: bool, out_dir: Option<PathBuf>) -> Self {
+        Session {
+            cargo,
+            out_dir,
+            temp_dir: None,
+            warnings: Vec::new(),
+            timings: Vec::new(),
+        }
+    }
+
+    // Runs `op` with everything it reports going to this session, which is handed back after
+    pub(crate) fn run<T>(self, op: impl FnOnce() -> T) -> (T, Session) {
+        let outer = SESSION.with(|session| session.replace(Some(self)));
+        let result = op();
+        let session = SESSION.with(|session| session.replace(outer));
+        (result, session.expect("the session is only taken here"))
+    }
+
+    // Ends the session, handing back its warnings and the time spent per phase
+    pub(crate) fn finish(mut self) -> (Vec<String>, Vec<(&'static str, Duration)>) {
+        (
+            std::mem::take(&mut self.warnings),
+            std::mem::take(&mut self.timings),
+        )
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(temp_dir) = &self.temp_dir {
+            let _ = fs::remove_dir_all(temp_dir);
+        }
+    }
+}
+
+fn with_session<T>(op: impl FnOnce(&mut Session) -> T) -> Option<T> {
+    SESSION.with(|session| session.borrow_mut().as_mut().map(op))
+}
+
+// Outside a session, e.g. in a unit test calling a single step, output is printed as the build
+// script would
+pub(crate) fn printing() -> bool {
+    with_session(|session| session.cargo).unwrap_or(true)
+}
+
+fn out_dir() -> Option<PathBuf> {
+    with_session(|session| session.out_dir.clone()).flatten()
+}
+
+// Where generated and unpacked hooks are written before being installed
+fn scratch_dir() -> Result<PathBuf> {
+    if let Some(out_dir) = out_dir() {
+        return Ok(out_dir);
+    }
+    if let Some(temp_dir) = with_session(|session| session.temp_dir.clone()).flatten() {
+        return Ok(temp_dir);
+    }
+    let temp_dir = private_temp_dir()?;
+    with_session(|session| session.temp_dir = Some(temp_dir.clone()));
+    Ok(temp_dir)
+}
+
+// A new directory under the system's temporary one that only this user can enter, so nobody else
+// can plant files or symlinks where hooks are unpacked, and no concurrent install shares it
+fn private_temp_dir() -> io::Result<PathBuf> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    loop {
+        let dir = env::temp_dir().join(format!(
+            "husky-rs-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        match builder.create(&dir) {
+            // Left behind by an earlier process with the same id, or planted by someone else
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => return result.map(|()| dir),
+        }
+    }
+}
+
+// Shown by cargo even without `-vv`, so reserved for problems the user should act on
+pub(crate) fn warn(message: &str) {
+    if LogLevel::Error <= log_level() {
+        cargo!("cargo:warning={}", message);
+    }
+    with_session(|session| session.warnings.push(message.to_string()));
+}
+
+pub(crate) fn timed<T>(phase: &'static str, op: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = op();
+    let elapsed = start.elapsed();
+    with_session(
+        |session| match session.timings.iter_mut().find(|(name, _)| *name == phase) {
+            Some((_, total)) => *total += elapsed,
+            None => session.timings.push((phase, elapsed)),
+        },
+    );
+    result
+}
+
+// Parallel builds of several crates depending on husky-rs create the same directories, and
+// losing that race can surface as `AlreadyExists` on some platforms, which is no failure
+fn create_hooks_dir(path: &Path) -> io::Result<()> {
+    match fs::create_dir_all(long_path(path)) {
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists && path.is_dir() => Ok(()),
+        result => result,
+    }
+}
+
+// A configured `mode` is applied exactly, regardless of the umask or an existing file's mode
+fn write_executable_file(path: &Path, content: &str, mode: Option<u32>) -> io::Result<()> {
+    write_executable_file_with(path, mode, |file| file.write_all(content.as_bytes()))
+}
+
+// Writes next to `path` and renames over it, so a failed write (e.g. on a full disk) never
+// leaves a truncated hook behind; the partial temporary file is removed instead
+fn write_executable_file_with(
+    path: &Path,
+    mode: Option<u32>,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.husky-tmp", file_name));
+    let result = (|| {
+        let mut file = create_executable_file(&tmp)?;
+        write(&mut file)?;
+        file.sync_all()?;
+        drop(file);
+        match mode {
+            Some(_) => set_hook_mode(&tmp, mode)?,
+            // Like rewriting in place would, keep the mode of the hook being replaced
+            None => {
+                if let Ok(metadata) = fs::metadata(long_path(path)) {
+                    fs::set_permissions(long_path(&tmp), metadata.permissions())?;
+                }
+            }
+        }
+        fs::rename(long_path(&tmp), long_path(path))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(long_path(&tmp));
+    }
+    result
+}
+
+// Retries `op` with exponential backoff while it fails with a lock held by someone else;
+// any other error, like a permission problem, is returned straight away
+fn retry_transient<T>(retries: u32, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut delay = std::time::Duration::from_millis(50);
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(err) if attempt < retries && is_transient(&err) => {
+                log!(LogLevel::Debug, "Retrying after transient error: {}", err);
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_transient(err: &io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    #[cfg(windows)]
+    const TRANSIENT_ERRORS: [i32; 2] = [32, 33];
+    // EBUSY and ETXTBSY
+    #[cfg(not(windows))]
+    const TRANSIENT_ERRORS: [i32; 2] = [16, 26];
+    err.raw_os_error()
+        .is_some_and(|code| TRANSIENT_ERRORS.contains(&code))
+}
+
+#[cfg(unix)]
+fn create_executable_file(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o755)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create_executable_file(path: &Path) -> io::Result<File> {
+    File::create(long_path(path))
+}
+
+#[cfg(unix)]
+fn set_hook_mode(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    match mode {
+        Some(mode) => fs::set_permissions(path, fs::Permissions::from_mode(mode)),
+        None => Ok(()),
+    }
+}
+
+// Git for Windows doesn't look at permission bits, `hook_mode` is warned about at load time
+#[cfg(not(unix))]
+fn set_hook_mode(_path: &Path, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+// Windows APIs reject paths beyond `MAX_PATH` (260 characters) unless they are given in
+// extended-length form, which deeply nested projects easily reach with `.git/hooks/<name>`
+#[cfg(windows)]
+fn long_path(path: &Path) -> PathBuf {
+    use std::path::{Component, Prefix};
+
+    const MAX_PATH: usize = 260;
+    // Extended-length paths are used verbatim, so only rewrite ones free of `.` and `..`
+    let normalized = path.components().all(|component| {
+        matches!(
+            component,
+            Component::Prefix(_) | Component::RootDir | Component::Normal(_)
+        )
+    });
+    if path.as_os_str().len() < MAX_PATH || !path.is_absolute() || !normalized {
+        return path.to_path_buf();
+    }
+    let text = path.display().to_string().replace('/', "\\");
+    match path.components().next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(_) => PathBuf::from(format!(r"\\?\{}", text)),
+            Prefix::UNC(..) => PathBuf::from(format!(r"\\?\UNC\{}", &text[2..])),
+            // Already in a verbatim or device form
+            _ => path.to_path_buf(),
+        },
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(not(windows))]
+fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{install_into, CLIENT_HOOKS};
+
+    pub(crate) fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("husky-rs-unit-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_valid_hook_names_are_unique() {
+        let mut names = VALID_HOOK_NAMES.to_vec();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), VALID_HOOK_NAMES.len());
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_strict_body_only_applies_to_shell_hooks() {
+        let config = Config {
+            strict_body: true,
+            ..Config::default()
+        };
+        let bash =
+            generate_husky_hook_script(lines("#!/usr/bin/env bash\necho hi"), &[], &[], &config);
+        assert!(
+            bash.contains(&"set -euo pipefail".to_string()),
+            "{:?}",
+            bash
+        );
+        let sh = generate_husky_hook_script(lines("#!/bin/sh\necho hi"), &[], &[], &config);
+        assert!(sh.contains(&"set -eu".to_string()), "{:?}", sh);
+        let python = generate_husky_hook_script(
+            lines("#!/usr/bin/env python3\nprint('hi')"),
+            &[],
+            &[],
+            &config,
+        );
+        assert!(
+            !python.iter().any(|line| line.starts_with("set -")),
+            "{:?}",
+            python
+        );
+
+        let bash = generate_husky_hook_script(
+            lines("#!/usr/bin/env bash\necho hi"),
+            &[],
+            &[],
+            &Config::default(),
+        );
+        assert!(
+            !bash.iter().any(|line| line.starts_with("set -")),
+            "{:?}",
+            bash
+        );
+    }
+
+    #[test]
+    fn test_absolute_interpreter_shebangs_are_kept() {
+        let config = Config::default();
+        let bash = generate_husky_hook_script(lines("#!/bin/bash\necho hi"), &[], &[], &config);
+        assert!(bash[0].starts_with("#!/bin/bash\n"), "{:?}", bash);
+        assert!(!bash.contains(&"#!/bin/bash".to_string()), "{:?}", bash);
+        assert!(bash.contains(&"echo hi".to_string()), "{:?}", bash);
+
+        let python =
+            generate_husky_hook_script(lines("#!/usr/bin/python3\nprint('hi')"), &[], &[], &config);
+        assert!(
+            python[0].starts_with("#!/usr/bin/python3\n"),
+            "{:?}",
+            python
+        );
+        assert!(
+            !python.iter().any(|line| line.contains("changed-files.sh")),
+            "{:?}",
+            python
+        );
+        assert!(
+            !bash.iter().any(|line| line.contains("changed-files.sh")),
+            "{:?}",
+            bash
+        );
+        let changed = generate_husky_hook_script(
+            lines("#!/bin/sh\nhusky_changed_files -- '*.rs'"),
+            &[],
+            &[],
+            &config,
+        );
+        assert!(
+            changed.contains(&". \"$(dirname -- \"$0\")/_/changed-files.sh\"".to_string()),
+            "{:?}",
+            changed
+        );
+
+        assert!(is_shebang("#!/usr/bin/env -S deno run"));
+        assert!(!is_shebang("#!"));
+        assert!(!is_shebang("# !/bin/sh"));
+    }
+
+    #[test]
+    fn test_is_dev_dependency() {
+        assert!(is_dev_dependency(
+            "[package]\nname = \"app\"\n\n[dev-dependencies]\nhusky-rs = { path = \"..\" }\n"
+        ));
+        assert!(is_dev_dependency(
+            "[target.'cfg(unix)'.dev-dependencies]\n\"husky-rs\" = \"0.1\"\n"
+        ));
+        assert!(is_dev_dependency(
+            "[dev-dependencies.husky-rs]\nversion = \"0.1\"\n"
+        ));
+        assert!(!is_dev_dependency(
+            "[dependencies]\nhusky-rs.workspace = true\n\n[dev-dependencies]\nhusky-rs = \"0.1\"\n"
+        ));
+        assert!(!is_dev_dependency(
+            "[package]\nname = \"husky-rs\"\n\n[dev-dependencies]\nflate2 = \"1\"\n"
+        ));
+    }
+
+    #[test]
+    fn test_env_files_are_not_hooks() {
+        assert_eq!(canonical_hook_name("pre-commit.env", &[]), None);
+        assert_eq!(
+            canonical_hook_name("pre-commit.sh", &[]),
+            Some(("pre-commit", None))
+        );
+    }
+
+    #[test]
+    fn test_build_hook_content() {
+        let header = format!(
+            "#\n# {}\n# v{}: {}\n",
+            HUSKY_HEADER,
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_HOMEPAGE")
+        );
+        let marker = format!("# {}\n#\n", HUSKY_MARKER);
+        let config = Config {
+            strict_body: true,
+            footer: Some("echo done".to_string()),
+            source_comment: true,
+            ..Config::default()
+        };
+        let content = build_hook_content(
+            lines("#!/bin/sh\necho hi\n"),
+            &[".husky/hooks/pre-commit".to_string()],
+            &[PathBuf::from("/repo/.husky/env")],
+            &config,
+        );
+        assert_eq!(
+            content,
+            format!(
+                "#!/bin/sh\n{}# source: .husky/hooks/pre-commit\n{}\n\
+                 . /repo/.husky/env\n\
+                 set -eu\n\
+                 echo hi\n\
+                 echo done\n\n",
+                header, marker
+            )
+        );
+
+        // Without a recognized shebang or a default one, hooks run with bash; other
+        // interpreters get no shell-only lines
+        let no_default = Config {
+            default_shebang: None,
+            ..Config::default()
+        };
+        let content = build_hook_content(lines("echo hi"), &[], &[], &no_default);
+        assert_eq!(
+            content,
+            format!("#!/usr/bin/env bash\n{}{}\necho hi\n", header, marker)
+        );
+        let content = build_hook_content(
+            lines("#!/usr/bin/env python3\nprint('hi')"),
+            &[],
+            &[PathBuf::from("/repo/.husky/env")],
+            &config,
+        );
+        assert_eq!(
+            content,
+            format!(
+                "#!/usr/bin/env python3\n{}{}\nprint('hi')\necho done\n\n",
+                header, marker
+            )
+        );
+    }
+
+    #[test]
+    fn test_env_file_paths_are_quoted() {
+        let script = generate_husky_hook_script(
+            lines("#!/bin/sh\necho hi"),
+            &[],
+            &[PathBuf::from("/my repo/it's $HOME/.husky/env")],
+            &Config::default(),
+        );
+        assert!(
+            script.contains(&r#". '/my repo/it'\''s $HOME/.husky/env'"#.to_string()),
+            "{:?}",
+            script
+        );
+    }
+
+    #[test]
+    fn test_minimal_header() {
+        let config = Config {
+            header_style: HeaderStyle::Minimal,
+            source_comment: true,
+            ..Config::default()
+        };
+        let content = build_hook_content(
+            lines("#!/usr/bin/env python3\nprint('hi')"),
+            &[".husky/hooks/pre-commit".to_string()],
+            &[],
+            &config,
+        );
+        assert_eq!(
+            content,
+            format!(
+                "#!/usr/bin/env python3\n# {} v{}\n# {}\nprint('hi')\n",
+                HUSKY_HEADER,
+                env!("CARGO_PKG_VERSION"),
+                HUSKY_MARKER
+            )
+        );
+    }
+
+    #[test]
+    fn test_header_version() {
+        let mut config = Config {
+            header_style: HeaderStyle::Minimal,
+            header_version: HeaderVersion::Pinned("1.0.0".to_string()),
+            ..Config::default()
+        };
+        let content = build_hook_content(lines("#!/bin/sh\necho hi"), &[], &[], &config);
+        assert!(
+            content.contains(&format!("# {} v1.0.0\n", HUSKY_HEADER)),
+            "{}",
+            content
+        );
+
+        config.header_version = HeaderVersion::Omitted;
+        let content = build_hook_content(lines("#!/bin/sh\necho hi"), &[], &[], &config);
+        assert!(
+            content.contains(&format!("# {}\n", HUSKY_HEADER)),
+            "{}",
+            content
+        );
+        assert!(!content.contains(env!("CARGO_PKG_VERSION")), "{}", content);
+
+        config.header_style = HeaderStyle::Full;
+        let content = build_hook_content(lines("#!/bin/sh\necho hi"), &[], &[], &config);
+        assert!(
+            content.contains(&format!("\n# {}\n", env!("CARGO_PKG_HOMEPAGE"))),
+            "{}",
+            content
+        );
+        assert!(!content.contains(env!("CARGO_PKG_VERSION")), "{}", content);
+    }
+
+    #[test]
+    fn test_header_width() {
+        let mut config = Config {
+            header_version: HeaderVersion::Pinned("1.0.0".to_string()),
+            header_width: Some(40),
+            source_comment: true,
+            ..Config::default()
+        };
+        let sources = vec![".husky/hooks/pre-commit".to_string()];
+        let content = build_hook_content(lines("#!/bin/sh\necho hi"), &sources, &[], &config);
+        let header: Vec<&str> = content
+            .lines()
+            .take_while(|line| line.starts_with('#'))
+            .collect();
+        assert!(
+            header.iter().all(|line| line.chars().count() <= 40),
+            "{}",
+            content
+        );
+        assert!(
+            content.contains(&format!("\n# v1.0.0:\n# {}\n", env!("CARGO_PKG_HOMEPAGE"))),
+            "{}",
+            content
+        );
+        assert!(is_managed_hook(&content), "{}", content);
+        assert_eq!(
+            describe_managed_hook(&content),
+            (Some("1.0.0".to_string()), sources.clone())
+        );
+
+        config.header_style = HeaderStyle::Minimal;
+        config.header_width = Some(20);
+        let content = build_hook_content(lines("#!/bin/sh\necho hi"), &[], &[], &config);
+        assert!(
+            content.contains("\n# This hook was set\n# by husky-rs v1.0.0\n"),
+            "{}",
+            content
+        );
+    }
+
+    #[test]
+    fn test_preserve_comments() {
+        let config = Config {
+            preserve_comments: true,
+            strict_body: true,
+            header_style: HeaderStyle::Minimal,
+            ..Config::default()
+        };
+        let content = build_hook_content(
+            lines("#!/bin/sh\n\n# Runs the linters\n#\n#   cargo clippy\n\necho hi\n"),
+            &[],
+            &[],
+            &config,
+        );
+        assert_eq!(
+            content,
+            format!(
+                "#!/bin/sh\n# {} v{}\n# {}\n\n# Runs the linters\n#\n#   cargo clippy\n\n\
+                 set -eu\necho hi\n",
+                HUSKY_HEADER,
+                env!("CARGO_PKG_VERSION"),
+                HUSKY_MARKER
+            )
+        );
+    }
+
+    #[test]
+    fn test_is_managed_hook() {
+        let content = build_hook_content(lines("#!/bin/sh\necho hi"), &[], &[], &Config::default());
+        assert!(is_managed_hook(&content));
+        // Rewording the human-readable header doesn't lose track of the hook
+        assert!(is_managed_hook(
+            &content.replace(HUSKY_HEADER, "Managed by our tooling")
+        ));
+        assert!(!is_managed_hook("#!/bin/sh\necho hi\n"));
+        assert!(!is_managed_hook(&format!(
+            "#!/bin/sh\necho '# {}'\n",
+            HUSKY_MARKER
+        )));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_host_default_shebang() {
+        if Path::new("/bin/sh").exists() {
+            assert_eq!(host_default_shebang(), "#!/bin/sh");
+        }
+        let config = Config {
+            default_shebang: Some(host_default_shebang()),
+            ..Config::default()
+        };
+        let content = build_hook_content(lines("echo hi"), &[], &[], &config);
+        assert!(content.starts_with(&host_default_shebang()), "{}", content);
+    }
+
+    #[test]
+    fn test_git_bash_pins_shell_hooks() {
+        let config = Config {
+            git_bash: Some(PathBuf::from(r"C:\Program Files\Git\bin\bash.exe")),
+            strict_body: true,
+            ..Config::default()
+        };
+        let content = build_hook_content(lines("#!/bin/sh\necho hi"), &[], &[], &config);
+        assert!(
+            content.starts_with("#!C:/Program Files/Git/bin/bash.exe\n"),
+            "{}",
+            content
+        );
+        assert!(content.contains("set -eu\n"), "{}", content);
+
+        let content =
+            build_hook_content(lines("#!/usr/bin/env python3\nprint()"), &[], &[], &config);
+        assert!(
+            content.starts_with("#!/usr/bin/env python3\n"),
+            "{}",
+            content
+        );
+    }
+
+    #[test]
+    fn test_canonical_shebang() {
+        let mut config = Config {
+            canonical_shebang: Some("#!/usr/bin/env bash".to_string()),
+            strict_body: true,
+            ..Config::default()
+        };
+        let content = build_hook_content(lines("#!/bin/sh\necho hi"), &[], &[], &config);
+        assert!(content.starts_with("#!/usr/bin/env bash\n"), "{}", content);
+        // Strict mode follows the shell the hook now runs with
+        assert!(content.contains("set -euo pipefail\n"), "{}", content);
+
+        let python = lines("#!/usr/bin/env python3\nprint()");
+        let content = build_hook_content(python.clone(), &[], &[], &config);
+        assert!(
+            content.starts_with("#!/usr/bin/env python3\n"),
+            "{}",
+            content
+        );
+        config.force_canonical_shebang = true;
+        let content = build_hook_content(python, &[], &[], &config);
+        assert!(content.starts_with("#!/usr/bin/env bash\n"), "{}", content);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_find_git_bash() {
+        use std::process::Command;
+
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let bash = find_git_bash().expect("bash.exe next to git");
+        assert!(bash.ends_with("bin/bash.exe"), "{}", bash.display());
+        assert!(Command::new(&bash)
+            .args(["-c", "exit 0"])
+            .status()
+            .unwrap()
+            .success());
+    }
+
+    #[test]
+    fn test_update_template_uses_arguments() {
+        let config = Config {
+            protected_refs: vec!["refs/heads/main".to_string(), "refs/tags/v1.0".to_string()],
+            ..Config::default()
+        };
+        let (name, content) = generated_hooks(&config).pop().unwrap();
+        assert_eq!(name, "update");
+        assert!(content.contains("refname=\"$1\""), "{}", content);
+        assert!(content.contains("oldrev=\"$2\""), "{}", content);
+        assert!(content.contains("newrev=\"$3\""), "{}", content);
+        assert!(
+            content.contains("    refs/heads/main | refs/tags/v1.0) ;;"),
+            "{}",
+            content
+        );
+        // Arguments only, `update` gets nothing on stdin
+        assert!(!content.contains("read "), "{}", content);
+    }
+
+    #[test]
+    fn test_footer_follows_body() {
+        let config = Config {
+            footer: Some("echo done".to_string()),
+            ..Config::default()
+        };
+        let script = generate_husky_hook_script(lines("#!/bin/sh\necho hi\n"), &[], &[], &config);
+        let body = script.iter().position(|line| line == "echo hi").unwrap();
+        let footer = script.iter().position(|line| line == "echo done").unwrap();
+        assert!(body < footer, "{:?}", script);
+        assert_eq!(script.last().map(String::as_str), Some(""));
+
+        let config = Config {
+            footer_on_exit: true,
+            ..config
+        };
+        let script = generate_husky_hook_script(lines("#!/bin/sh\necho hi\n"), &[], &[], &config);
+        let trap = script
+            .iter()
+            .position(|line| line == "trap husky_footer EXIT")
+            .unwrap();
+        let body = script.iter().position(|line| line == "echo hi").unwrap();
+        assert!(trap < body, "{:?}", script);
+    }
+
+    #[test]
+    fn test_source_comment() {
+        let sources = ["githooks/pre-commit.fmt.sh".to_string()];
+        let script = generate_husky_hook_script(
+            lines("#!/bin/sh\necho hi"),
+            &sources,
+            &[],
+            &Config::default(),
+        );
+        assert!(
+            script[0].contains("# source: githooks/pre-commit.fmt.sh\n"),
+            "{:?}",
+            script
+        );
+        let script = generate_husky_hook_script(
+            lines("#!/bin/sh\necho hi"),
+            &sources,
+            &[],
+            &Config {
+                source_comment: false,
+                ..Config::default()
+            },
+        );
+        assert!(!script[0].contains("# source:"), "{:?}", script);
+    }
+
+    fn fake_env(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/dev".to_string()),
+            "HOOKS" => Some("shared/hooks".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_path_expands_home() {
+        assert_eq!(
+            expand_path("~/hooks", fake_env).unwrap(),
+            PathBuf::from("/home/dev/hooks")
+        );
+        assert_eq!(
+            expand_path("$HOME/hooks", fake_env).unwrap(),
+            PathBuf::from("/home/dev/hooks")
+        );
+        assert_eq!(
+            expand_path("${HOME}/.husky/${HOOKS}", fake_env).unwrap(),
+            PathBuf::from("/home/dev/.husky/shared/hooks")
+        );
+    }
+
+    #[test]
+    fn test_expand_path_keeps_literal_paths() {
+        for literal in [".husky/hooks", "/opt/hooks", "a~b/$/c", "~user/hooks"] {
+            assert_eq!(
+                expand_path(literal, fake_env).unwrap(),
+                PathBuf::from(literal)
+            );
+        }
+        let error = expand_path("$MISSING/hooks", fake_env).unwrap_err();
+        assert!(error.contains("MISSING"), "{}", error);
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn test_extract_hooks_archive_from_zip() {
+        use std::io::Write as _;
+
+        let dir = temp_dir("zip-archive");
+        let archive = dir.join("hooks.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, content) in [
+            ("hooks/pre-commit.sh", "echo pre-commit"),
+            ("hooks/README.md", "not a hook"),
+            ("../commit-msg", "echo commit-msg"),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let dest = dir.join("out");
+        extract_hooks_archive(&archive, &dest, &[]).unwrap();
+        let mut extracted: Vec<_> = fs::read_dir(&dest)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        extracted.sort();
+        assert_eq!(extracted, ["commit-msg", "pre-commit.sh"]);
+        assert_eq!(
+            fs::read_to_string(dest.join("pre-commit.sh")).unwrap(),
+            "echo pre-commit"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_interpreter_available() {
+        assert!(interpreter_available("#!/bin/sh"));
+        assert!(interpreter_available("#!/usr/bin/env sh"));
+        assert!(!interpreter_available(
+            "#!/usr/bin/env husky-no-such-interpreter"
+        ));
+        assert!(!interpreter_available("#!/opt/husky/no-such-interpreter"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_write_executable_file_beyond_max_path() {
+        let dir = temp_dir("long-path");
+        let mut hooks_dir = dir.clone();
+        while hooks_dir.as_os_str().len() < 300 {
+            hooks_dir.push("a-deeply-nested-project-directory");
+        }
+        hooks_dir.push(".git");
+        hooks_dir.push("hooks");
+        fs::create_dir_all(long_path(&hooks_dir)).unwrap();
+
+        let hook = hooks_dir.join("pre-commit");
+        write_executable_file(&hook, "echo hi\n", None).unwrap();
+        assert_eq!(fs::read_to_string(long_path(&hook)).unwrap(), "echo hi\n");
+        fs::remove_dir_all(long_path(&dir)).unwrap();
+    }
+
+    #[test]
+    fn test_create_hooks_dir_races() {
+        let dir = temp_dir("hooks-dir-race");
+        let hooks_dir = dir.join(".git").join("hooks");
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let hooks_dir = hooks_dir.clone();
+                std::thread::spawn(move || create_hooks_dir(&hooks_dir))
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+        // Already there
+        create_hooks_dir(&hooks_dir).unwrap();
+
+        let file = dir.join("not-a-dir");
+        fs::write(&file, "").unwrap();
+        assert!(create_hooks_dir(&file).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_workspace_metadata_is_overridden() {
+        let dir = temp_dir("workspace-metadata");
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"app\"]\n\n\
+             [workspace.metadata.husky]\nserver_hooks = true\nstrict_body = true\n",
+        )
+        .unwrap();
+        let manifest = dir.join("app").join("Cargo.toml");
+        fs::create_dir_all(manifest.parent().unwrap()).unwrap();
+        fs::write(
+            &manifest,
+            "[package]\nname = \"app\"\n\n[package.metadata.husky]\nstrict_body = false\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&manifest).unwrap();
+        assert!(config.server_hooks);
+        assert!(!config.strict_body);
+        assert!(!is_workspace_manifest(
+            &fs::read_to_string(&manifest).unwrap()
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into() {
+        let dir = temp_dir("install-into");
+        let init = std::process::Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(&dir)
+            .status()
+            .unwrap();
+        assert!(init.success());
+        let user_hooks_dir = dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR);
+        fs::create_dir_all(&user_hooks_dir).unwrap();
+        fs::write(
+            user_hooks_dir.join("pre-commit"),
+            "#!/bin/sh\necho explicit\n",
+        )
+        .unwrap();
+
+        install_into(&dir, &Config::default()).unwrap();
+        let hook = dir.join(".git").join("hooks").join("pre-commit");
+        let installed = fs::read_to_string(hook).unwrap();
+        assert!(is_managed_hook(&installed), "{}", installed);
+        assert!(installed.contains("echo explicit"), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // A repository for `install_into` without git or cargo: only the `.git` directory discovery
+    // looks for, and the given hook sources
+    pub(crate) fn temp_repo(name: &str, hooks: &[(&str, &str)]) -> PathBuf {
+        let dir = temp_dir(name);
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        let user_hooks_dir = dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR);
+        fs::create_dir_all(&user_hooks_dir).unwrap();
+        for (name, content) in hooks {
+            fs::write(user_hooks_dir.join(name), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_install_into_valid_hooks() {
+        let names = ["pre-commit", "prepare-commit-msg", "commit-msg", "pre-push"];
+        let hooks: Vec<_> = names
+            .iter()
+            .map(|name| (*name, "#!/bin/sh\necho \"This is a test hook\"\n"))
+            .collect();
+        let dir = temp_repo("install-into-valid", &hooks);
+
+        install_into(&dir, &Config::default()).unwrap();
+        for name in names {
+            let hook = dir.join(".git").join("hooks").join(name);
+            let installed = fs::read_to_string(&hook).unwrap();
+            assert!(is_managed_hook(&installed), "{}", installed);
+            assert!(installed.contains("This is a test hook"), "{}", installed);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = fs::metadata(&hook).unwrap().permissions().mode();
+                assert_ne!(mode & 0o111, 0, "'{}' is not executable", hook.display());
+            }
+        }
+        // A second run finds everything up to date
+        let before = fs::read_to_string(dir.join(".git").join("hooks").join("pre-push"));
+        install_into(&dir, &Config::default()).unwrap();
+        let after = fs::read_to_string(dir.join(".git").join("hooks").join("pre-push"));
+        assert_eq!(before.unwrap(), after.unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_empty_hook() {
+        let dir = temp_repo(
+            "install-into-empty",
+            &[
+                ("pre-commit", "#!/bin/sh\n\n"),
+                ("pre-push", "#!/bin/sh\ntrue\n"),
+            ],
+        );
+
+        let error = install_into(&dir, &Config::default()).unwrap_err();
+        assert!(
+            matches!(&error, HuskyError::EmptyUserHook(path) if path.ends_with("pre-commit")),
+            "{}",
+            error
+        );
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert!(!hooks_dir.join("pre-commit").exists());
+        // The other hooks are installed all the same
+        assert!(hooks_dir.join("pre-push").is_file());
+
+        let config = Config {
+            empty_hook: EmptyHookPolicy::Warn,
+            ..Config::default()
+        };
+        let warnings = install_into(&dir, &config).unwrap();
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning.contains("User hook script is empty")),
+            "{:?}",
+            warnings
+        );
+        assert!(!hooks_dir.join("pre-commit").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_oversized_raw_hook() {
+        let dir = temp_repo(
+            "install-into-oversized",
+            &[
+                ("pre-commit", &format!("#!/bin/sh\n#{}\n", "x".repeat(64))),
+                ("pre-push", "#!/bin/sh\ntrue\n"),
+            ],
+        );
+        let config = Config {
+            raw: true,
+            max_hook_size: 64,
+            ..Config::default()
+        };
+
+        let error = install_into(&dir, &config).unwrap_err();
+        assert!(
+            matches!(&error, HuskyError::HookTooLarge(path, 76, 64) if path.ends_with("pre-commit")),
+            "{}",
+            error
+        );
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert!(!hooks_dir.join("pre-commit").exists());
+        assert_eq!(
+            fs::read_to_string(hooks_dir.join("pre-push")).unwrap(),
+            "#!/bin/sh\ntrue\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_symlinked_hooks_dir() {
+        let dir = temp_repo(
+            "install-into-symlink",
+            &[("pre-commit", "#!/bin/sh\ntrue\n")],
+        );
+        let shared = dir.join("shared-hooks");
+        fs::create_dir_all(&shared).unwrap();
+        std::os::unix::fs::symlink(&shared, dir.join(".git").join("hooks")).unwrap();
+
+        install_into(&dir, &Config::default()).unwrap();
+        let installed = fs::read_to_string(shared.join("pre-commit")).unwrap();
+        assert!(is_managed_hook(&installed), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_retry_transient() {
+        let transient = || io::Error::from_raw_os_error(if cfg!(windows) { 32 } else { 16 });
+
+        let mut attempts = 0;
+        let result = retry_transient(3, || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(transient())
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut attempts = 0;
+        let result = retry_transient(3, || -> io::Result<()> {
+            attempts += 1;
+            Err(io::Error::from(io::ErrorKind::PermissionDenied))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result = retry_transient(2, || -> io::Result<()> {
+            attempts += 1;
+            Err(transient())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_source_read_error() {
+        let path = Path::new(".husky/hooks/pre-commit");
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        let error = source_read_error(path, denied.into());
+        assert!(matches!(&error, HuskyError::HookNotReadable(p) if p == path));
+        assert_eq!(
+            error.to_string(),
+            "Hook source '.husky/hooks/pre-commit' cannot be read, check its permissions"
+        );
+        let missing = io::Error::from(io::ErrorKind::NotFound);
+        assert!(matches!(
+            source_read_error(path, missing.into()),
+            HuskyError::Io(_)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_git_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("unreadable-git");
+        let git_dir = dir.join(".git");
+        fs::create_dir_all(&git_dir).unwrap();
+        fs::set_permissions(&git_dir, fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions don't apply to root
+        let readable = fs::read_dir(&git_dir).is_ok();
+        let result = check_git_dir_readable(&git_dir);
+        fs::set_permissions(&git_dir, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        if readable {
+            return;
+        }
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("cannot be read"), "{}", message);
+        assert!(message.contains("check its permissions"), "{}", message);
+    }
+
+    #[test]
+    fn test_payload_reference() {
+        let payload = "#!/bin/sh\necho shared\n";
+        let hash = content_hash(payload);
+        assert_eq!(hash.len(), 16);
+        assert_eq!(hash, content_hash(payload));
+        assert_ne!(hash, content_hash("#!/bin/sh\necho other\n"));
+
+        let payload_path = Path::new(".git/hooks").join(PAYLOAD_DIR).join(&hash);
+        let launcher = build_launcher(&payload_path, &[], &Config::default());
+        assert_eq!(payload_reference(&launcher), Some(hash.as_str()));
+        assert_eq!(payload_reference("#!/bin/sh\necho inline\n"), None);
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("0700"), Some(0o700));
+        assert_eq!(parse_mode("755"), Some(0o755));
+        assert_eq!(parse_mode("0o750"), Some(0o750));
+        assert_eq!(parse_mode("0800"), None);
+        assert_eq!(parse_mode("rwx"), None);
+        assert_eq!(parse_mode("07777"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_executable_file_with_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("hook-mode");
+        let hook = dir.join("pre-commit");
+        write_executable_file(&hook, "echo hi\n", None).unwrap();
+        write_executable_file(&hook, "echo hi\n", Some(0o700)).unwrap();
+        let mode = fs::metadata(&hook).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_failure_keeps_previous_hook() {
+        let dir = temp_dir("disk-full");
+        let hook = dir.join("pre-commit");
+        write_executable_file(&hook, "echo old\n", None).unwrap();
+
+        let err = write_executable_file_with(&hook, None, |file| {
+            file.write_all(b"echo ne")?;
+            #[cfg(not(windows))]
+            return Err(io::Error::from_raw_os_error(28));
+            #[cfg(windows)]
+            return Err(io::Error::from_raw_os_error(112));
+        })
+        .unwrap_err();
+        assert!(matches!(write_error(&hook, err), HuskyError::DiskFull(path) if path == hook));
+        assert_eq!(fs::read_to_string(&hook).unwrap(), "echo old\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let err = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        assert!(matches!(write_error(&hook, err), HuskyError::Io(_)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_plan_hooks() {
+        let dir = temp_dir("plan");
+        let hooks_dir = dir.join("hooks");
+        let git_hooks_dir = dir.join("git-hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+        fs::create_dir_all(&git_hooks_dir).unwrap();
+        fs::write(hooks_dir.join("pre-commit.sh"), "echo valid\n").unwrap();
+        fs::write(hooks_dir.join("not-a-hook.sh"), "echo invalid\n").unwrap();
+        // Emptiness is only found out when the sources are read during installation
+        fs::write(hooks_dir.join("pre-push"), "").unwrap();
+        fs::write(hooks_dir.join("commit-msg"), "echo disabled\n").unwrap();
+        fs::write(hooks_dir.join("pre-receive"), "echo server\n").unwrap();
+        fs::write(hooks_dir.join("post-merge"), "echo existing\n").unwrap();
+        fs::write(git_hooks_dir.join("post-merge"), "echo mine\n").unwrap();
+
+        let hooks = discover_hooks(&hooks_dir, &[]).unwrap();
+        let disabled = vec!["commit-msg".to_string()];
+        let config = Config {
+            only_missing: true,
+            ..Config::default()
+        };
+        let plan: Vec<_> = plan_hooks(&hooks, &disabled, &git_hooks_dir, &config)
+            .into_iter()
+            .map(|planned| (planned.name, planned.dst, planned.action))
+            .collect();
+        assert_eq!(
+            plan,
+            [
+                (
+                    "commit-msg",
+                    git_hooks_dir.join("commit-msg"),
+                    HookAction::SkipDisabled
+                ),
+                (
+                    "post-merge",
+                    git_hooks_dir.join("post-merge"),
+                    HookAction::Keep
+                ),
+                (
+                    "pre-commit",
+                    git_hooks_dir.join("pre-commit"),
+                    HookAction::Install
+                ),
+                (
+                    "pre-push",
+                    git_hooks_dir.join("pre-push"),
+                    HookAction::Install
+                ),
+                (
+                    "pre-receive",
+                    git_hooks_dir.join("pre-receive"),
+                    HookAction::SkipServerSide
+                ),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        for messy in [
+            ".husky/hooks",
+            ".husky/hooks/",
+            "./.husky/hooks",
+            ".husky//hooks",
+            "./.husky/./hooks/",
+            ".husky/scripts/../hooks",
+        ] {
+            assert_eq!(
+                normalize_path(Path::new(messy)),
+                Path::new(".husky/hooks"),
+                "{}",
+                messy
+            );
+        }
+        if cfg!(windows) {
+            assert_eq!(
+                normalize_path(Path::new(r".\.husky\hooks\")),
+                Path::new(".husky/hooks")
+            );
+        }
+        assert_eq!(
+            normalize_path(Path::new("../shared/hooks/")),
+            Path::new("../shared/hooks")
+        );
+        assert_eq!(normalize_path(Path::new("/../hooks")), Path::new("/hooks"));
+        assert_eq!(normalize_path(Path::new("./")), Path::new("."));
+    }
+
+    #[test]
+    fn test_describe_managed_hook() {
+        let config = Config {
+            source_comment: true,
+            ..Config::default()
+        };
+        let sources = [".husky/hooks/pre-commit.sh".to_string()];
+        let content = build_hook_content(lines("#!/bin/sh\necho hi"), &sources, &[], &config);
+        assert_eq!(
+            describe_managed_hook(&content),
+            (
+                Some(env!("CARGO_PKG_VERSION").to_string()),
+                sources.to_vec()
+            )
+        );
+
+        let config = Config {
+            header_style: HeaderStyle::Minimal,
+            header_version: HeaderVersion::Pinned("2.0.0".to_string()),
+            ..Config::default()
+        };
+        let content = build_hook_content(lines("#!/bin/sh\necho v1"), &[], &[], &config);
+        assert_eq!(
+            describe_managed_hook(&content),
+            (Some("2.0.0".to_string()), Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_resolve_ambiguous_sources() {
+        let source = |path: &str, label: Option<&str>| HookSource {
+            path: PathBuf::from(path),
+            label: label.map(str::to_string),
+        };
+        let paths = |sources: &[HookSource]| -> Vec<PathBuf> {
+            sources.iter().map(|source| source.path.clone()).collect()
+        };
+
+        let mut sources = vec![
+            source("hooks/pre-commit.fmt.sh", Some("fmt")),
+            source("hooks/pre-commit.sh", None),
+            source("hooks/pre-commit", None),
+        ];
+        resolve_ambiguous_sources("pre-commit", &mut sources, false).unwrap();
+        assert_eq!(paths(&sources), [PathBuf::from("hooks/pre-commit")]);
+
+        let mut sources = vec![
+            source("hooks/pre-commit.sh", None),
+            source("hooks/pre-commit.py", None),
+        ];
+        resolve_ambiguous_sources("pre-commit", &mut sources, false).unwrap();
+        assert_eq!(paths(&sources), [PathBuf::from("hooks/pre-commit.py")]);
+
+        let mut fragments = vec![
+            source("hooks/pre-commit.fmt.sh", Some("fmt")),
+            source("hooks/pre-commit.test.sh", Some("test")),
+        ];
+        resolve_ambiguous_sources("pre-commit", &mut fragments, true).unwrap();
+        assert_eq!(fragments.len(), 2);
+
+        let mut sources = vec![
+            source("hooks/pre-commit", None),
+            source("hooks/pre-commit.sh", None),
+        ];
+        assert!(matches!(
+            resolve_ambiguous_sources("pre-commit", &mut sources, true),
+            Err(HuskyError::AmbiguousHook(..))
+        ));
+    }
+
+    #[test]
+    fn test_empty_hook_policy() {
+        let dir = temp_dir("empty-hook");
+        let hooks_dir = dir.join("hooks");
+        let git_hooks_dir = dir.join("git-hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+        fs::create_dir_all(&git_hooks_dir).unwrap();
+        fs::write(hooks_dir.join("pre-commit"), "#!/bin/sh\n\n").unwrap();
+        let sources = [HookSource {
+            path: hooks_dir.join("pre-commit"),
+            label: None,
+        }];
+        let install = |empty_hook| {
+            let config = Config {
+                empty_hook,
+                ..Config::default()
+            };
+            install_hook("pre-commit", &sources, &[], &dir, &git_hooks_dir, &config)
+        };
+
+        assert!(matches!(
+            install(EmptyHookPolicy::Error),
+            Err(HuskyError::EmptyUserHook(path)) if path == sources[0].path
+        ));
+        assert!(matches!(
+            install(EmptyHookPolicy::Warn),
+            Ok(InstallOutcome::Empty)
+        ));
+        assert!(matches!(
+            install(EmptyHookPolicy::Skip),
+            Ok(InstallOutcome::Empty)
+        ));
+        assert!(!git_hooks_dir.join("pre-commit").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_require_shebang() {
+        let dir = temp_dir("require-shebang");
+        let hooks_dir = dir.join("hooks");
+        let git_hooks_dir = dir.join("git-hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+        fs::create_dir_all(&git_hooks_dir).unwrap();
+        fs::write(hooks_dir.join("pre-commit"), "echo no shebang\n").unwrap();
+        let sources = [HookSource {
+            path: hooks_dir.join("pre-commit"),
+            label: None,
+        }];
+        let install = |require_shebang| {
+            let config = Config {
+                require_shebang,
+                ..Config::default()
+            };
+            install_hook("pre-commit", &sources, &[], &dir, &git_hooks_dir, &config)
+        };
+
+        assert!(matches!(
+            install(true),
+            Err(HuskyError::MissingShebang(path)) if path == sources[0].path
+        ));
+        assert!(!git_hooks_dir.join("pre-commit").exists());
+        assert!(matches!(install(false), Ok(InstallOutcome::Installed)));
+        let installed = fs::read_to_string(git_hooks_dir.join("pre-commit")).unwrap();
+        assert!(installed.starts_with("#!"), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_header_timestamp() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(4_102_444_799), "2099-12-31T23:59:59Z");
+
+        let dir = temp_dir("header-timestamp");
+        let hooks_dir = dir.join("hooks");
+        let git_hooks_dir = dir.join("git-hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+        fs::create_dir_all(&git_hooks_dir).unwrap();
+        fs::write(hooks_dir.join("pre-commit"), "#!/bin/sh\necho hi\n").unwrap();
+        let sources = [HookSource {
+            path: hooks_dir.join("pre-commit"),
+            label: None,
+        }];
+        let install = |header_timestamp, check| {
+            let config = Config {
+                header_timestamp,
+                check,
+                ..Config::default()
+            };
+            install_hook("pre-commit", &sources, &[], &dir, &git_hooks_dir, &config).unwrap()
+        };
+
+        assert!(matches!(install(false, false), InstallOutcome::Installed));
+        assert!(matches!(install(false, false), InstallOutcome::UpToDate));
+        assert!(matches!(install(true, false), InstallOutcome::Installed));
+        let installed = fs::read_to_string(git_hooks_dir.join("pre-commit")).unwrap();
+        let timestamp = installed
+            .lines()
+            .find_map(|line| line.strip_prefix("# installed: "))
+            .unwrap();
+        assert_eq!(
+            timestamp.len(),
+            "2000-02-29T00:00:00Z".len(),
+            "{}",
+            installed
+        );
+        assert!(is_managed_hook(&installed), "{}", installed);
+        // Rewritten even within the same second
+        assert!(matches!(install(true, false), InstallOutcome::Installed));
+        assert!(matches!(install(true, true), InstallOutcome::UpToDate));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sources_fingerprint_follows_metadata() {
+        let dir = temp_dir("sources-fingerprint");
+        let path = dir.join("pre-commit");
+        fs::write(&path, "#!/bin/sh\necho one\n").unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        let sources = [HookSource {
+            path: path.clone(),
+            label: None,
+        }];
+        let config = Config::default();
+        let fingerprint = || sources_fingerprint(&sources, &config);
+        let before = fingerprint().unwrap();
+        assert_eq!(fingerprint().unwrap(), before);
+
+        fs::write(&path, "#!/bin/sh\necho three\n").unwrap();
+        assert_ne!(fingerprint().unwrap(), before);
+
+        // Another file of the same size and modification time, as an editor's save leaves it
+        let replacement = dir.join("pre-commit.new");
+        fs::write(&replacement, "#!/bin/sh\necho one\n").unwrap();
+        for file in [&path, &replacement] {
+            File::options()
+                .write(true)
+                .open(file)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        let rewritten = fingerprint().unwrap();
+        fs::rename(&replacement, &path).unwrap();
+        if cfg!(unix) {
+            assert_ne!(fingerprint().unwrap(), rewritten);
+        }
+
+        let generated = Config {
+            generators: true,
+            ..Config::default()
+        };
+        assert_eq!(sources_fingerprint(&sources, &generated), None);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(fingerprint(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    thread_local! {
+        // How often hook sources were read on this thread, to tell a cache hit from a read
+        pub(super) static SOURCE_READS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    #[test]
+    fn test_read_cache_skips_unchanged_sources() {
+        let dir = temp_repo(
+            "read-cache",
+            &[("pre-commit", "#!/bin/sh\necho \"This is a test hook\"\n")],
+        );
+        let reads = || SOURCE_READS.with(|reads| reads.get());
+        let hook = dir.join(".git").join("hooks").join("pre-commit");
+        install_into(&dir, &Config::default()).unwrap();
+        assert!(dir.join(".git").join(READ_CACHE_DIR).is_dir());
+
+        // Only the header changes, so the body comes from the cache
+        let pinned = Config {
+            header_version: HeaderVersion::Pinned("0.0.1".to_string()),
+            ..Config::default()
+        };
+        let before = reads();
+        install_into(&dir, &pinned).unwrap();
+        assert_eq!(reads(), before);
+        let installed = fs::read_to_string(&hook).unwrap();
+        assert!(installed.contains("v0.0.1"), "{}", installed);
+        assert!(installed.contains("This is a test hook"), "{}", installed);
+
+        fs::write(
+            dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR).join("pre-commit"),
+            "#!/bin/sh\necho \"edited since\"\n",
+        )
+        .unwrap();
+        install_into(&dir, &Config::default()).unwrap();
+        assert_eq!(reads(), before + 1);
+        let installed = fs::read_to_string(&hook).unwrap();
+        assert!(installed.contains("edited since"), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_git_version_requirements() {
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-146)\n"),
+            Some((2, 39))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.45.1.windows.1"),
+            Some((2, 45))
+        );
+        assert_eq!(parse_git_version(" 2.36 "), Some((2, 36)));
+        assert_eq!(parse_git_version("3"), Some((3, 0)));
+        assert_eq!(parse_git_version("latest"), None);
+
+        let warning =
+            git_version_warning("Hook 'reference-transaction'", (2, 28), Some((2, 20))).unwrap();
+        assert!(warning.contains("needs git >= 2.28"), "{}", warning);
+        assert!(warning.contains("git 2.20 is installed"), "{}", warning);
+        assert_eq!(git_version_warning("Hook", (2, 28), Some((2, 28))), None);
+        assert_eq!(git_version_warning("Hook", (2, 28), Some((3, 0))), None);
+        assert_eq!(git_version_warning("Hook", (2, 28), None), None);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.lib.sh", "pre-commit.lib.sh"));
+        assert!(glob_match("pre-commit", "pre-commit"));
+        assert!(glob_match("pre-?ush", "pre-push"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(!glob_match("*.lib.sh", "pre-commit.sh"));
+        assert!(!glob_match("pre-commit", "pre-commit.sh"));
+        assert!(!glob_match("a*b*c", "aXXbYY"));
+    }
+
+    #[test]
+    fn test_server_hooks_are_valid_hook_names() {
+        assert!(SERVER_HOOKS
+            .iter()
+            .all(|hook| VALID_HOOK_NAMES.contains(hook)));
+    }
+
+    #[test]
+    fn test_client_and_server_hooks_split_valid_hook_names() {
+        let mut union: Vec<_> = CLIENT_HOOKS.iter().chain(&SERVER_HOOKS).collect();
+        union.sort_unstable();
+        let mut valid: Vec<_> = VALID_HOOK_NAMES.iter().collect();
+        valid.sort_unstable();
+        assert_eq!(union, valid);
+        assert!(CLIENT_HOOKS.iter().all(|hook| !SERVER_HOOKS.contains(hook)));
+
+        assert!(CLIENT_HOOKS.contains(&"pre-commit"));
+        assert!(CLIENT_HOOKS.contains(&"pre-push"));
+        assert!(CLIENT_HOOKS.contains(&"post-checkout"));
+        assert!(SERVER_HOOKS.contains(&"pre-receive"));
+        assert!(SERVER_HOOKS.contains(&"update"));
+        assert!(!CLIENT_HOOKS.contains(&"post-receive"));
+    }
+
+    #[test]
+    fn test_parse_table_reads_only_the_requested_table() {
+        let manifest = r#"
+[package]
+name = "demo" # server_hooks = true
+
+[package.metadata.husky]
+server_hooks = true # install them all
+label = "a # not a comment"
+literal = 'C:\hooks'
+limit = 1_000
+hooks = [
+    "pre-commit", # first
+    "pre-push",
+]
+
+[dependencies]
+only_missing = true
+"#;
+        let table = parse_table(manifest, Some(METADATA_TABLE)).unwrap();
+        assert_eq!(table.len(), 5);
+        assert_eq!(table["server_hooks"], ConfigValue::Bool(true));
+        assert_eq!(
+            table["label"],
+            ConfigValue::String("a # not a comment".to_string())
+        );
+        assert_eq!(
+            table["literal"],
+            ConfigValue::String("C:\\hooks".to_string())
+        );
+        assert_eq!(table["limit"], ConfigValue::Integer(1000));
+        assert_eq!(
+            table["hooks"],
+            ConfigValue::Array(vec!["pre-commit".to_string(), "pre-push".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_table_rejects_malformed_values() {
+        let manifest = "[package.metadata.husky]\nserver_hooks = yes\n";
+        let error = parse_table(manifest, Some(METADATA_TABLE)).unwrap_err();
+        assert!(error.contains("server_hooks"), "{}", error);
+    }
+
+    #[test]
+    fn test_default_config_matches_load() {
+        let dir = temp_dir("default-config");
+        let loaded = Config::load(&dir.join("Cargo.toml")).unwrap();
+        if env::vars().all(|(name, _)| !name.starts_with("HUSKY_")) {
+            assert_eq!(format!("{:?}", loaded), format!("{:?}", Config::default()));
+        }
+        let config = Config::default();
+        assert!(config.source_comment && config.check_ignored);
+        assert_eq!(config.write_retries, DEFAULT_WRITE_RETRIES);
+        assert_eq!(config.max_hook_size, DEFAULT_MAX_HOOK_SIZE);
+        assert_eq!(
+            Config::from_metadata("").unwrap().max_hook_size,
+            DEFAULT_MAX_HOOK_SIZE
+        );
+        assert_eq!(config.default_shebang, Some(host_default_shebang()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_from_metadata() {
+        let config = Config::from_metadata(
+            "raw = true # copy them as is\nrequired_hooks = [\n  \"pre-commit\",\n]\nwrite_retries = 0",
+        )
+        .unwrap();
+        assert!(config.raw);
+        assert_eq!(config.required_hooks, ["pre-commit"]);
+        assert_eq!(config.write_retries, 0);
+        assert!(config.source_comment);
+
+        // Switches the build script only takes from the environment
+        let config = Config::from_metadata("check = true\nexport = true").unwrap();
+        assert!(config.check && config.export && !config.clean);
+
+        let error = Config::from_metadata("write_retries = -1").unwrap_err();
+        assert!(error.to_string().contains("write_retries"), "{}", error);
+        let error = Config::from_metadata("raw").unwrap_err();
+        assert!(error.to_string().contains("key = value"), "{}", error);
+    }
+
+    #[test]
+    fn test_canonical_hook_name_accepts_rare_hooks() {
+        for name in [
+            "reference-transaction",
+            "proc-receive",
+            "fsmonitor-watchman",
+        ] {
+            assert_eq!(canonical_hook_name(name, &[]), Some((name, None)));
+        }
+        assert_eq!(
+            canonical_hook_name("fsmonitor-watchman.pl", &[]),
+            Some(("fsmonitor-watchman", None))
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_with_context_reports_missing_path() {
+        let missing = env::temp_dir().join("husky-rs-missing").join("nested");
+        let error =
+            canonicalize_with_context(&missing, "git directory referenced by '.git'").unwrap_err();
+        assert!(matches!(error, HuskyError::PathResolution(..)));
+        let message = error.to_string();
+        assert!(
+            message.contains(&missing.display().to_string()),
+            "{}",
+            message
+        );
+        assert!(
+            message.contains("git directory referenced by '.git'"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_read_git_submodule_resolves_relative_gitdir() {
+        let dir = temp_dir("gitdir");
+        let module_dir = dir.join(".git").join("modules").join("sub");
+        fs::create_dir_all(&module_dir).unwrap();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let git_file = dir.join("sub").join(".git");
+        fs::write(&git_file, "gitdir: ../.git/modules/sub\n").unwrap();
+
+        let git_dir = read_git_submodule(&git_file).unwrap();
+        assert_eq!(git_dir, fs::canonicalize(&module_dir).unwrap());
+
+        fs::remove_dir_all(&module_dir).unwrap();
+        let error = read_git_submodule(&git_file).unwrap_err();
+        assert!(
+            error.to_string().contains(&git_file.display().to_string()),
+            "{}",
+            error
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Runs git in `dir`, whatever repository the tests themselves run in
+    pub(crate) fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_COMMON_DIR")
+            .status()
+            .unwrap();
+        assert!(
+            status.success(),
+            "git {:?} failed in '{}'",
+            args,
+            dir.display()
+        );
+    }
+
+    // Runs an installed hook the way git would, from the root of the repository at `dir`
+    fn run_hook(hook: &Path, dir: &Path, args: &[&str]) -> std::process::Output {
+        std::process::Command::new(hook)
+            .args(args)
+            .current_dir(dir)
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_COMMON_DIR")
+            .output()
+            .unwrap()
+    }
+
+    pub(crate) const TEST_HOOK: &str = "#!/bin/sh\necho \"This is a test hook\"\n";
+
+    #[test]
+    fn test_install_into_hook_extension() {
+        let dir = temp_repo("install-into-extension", &[("pre-commit.sh", TEST_HOOK)]);
+
+        install_into(&dir, &Config::default()).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        let installed = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+        assert!(is_managed_hook(&installed), "{}", installed);
+        assert!(installed.contains("This is a test hook"), "{}", installed);
+        assert!(!hooks_dir.join("pre-commit.sh").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_ambiguous_sources() {
+        let dir = temp_repo(
+            "install-into-ambiguous",
+            &[
+                ("pre-commit.sh", "#!/bin/sh\necho \"From pre-commit.sh\"\n"),
+                ("pre-commit", "#!/bin/sh\necho \"From pre-commit\"\n"),
+            ],
+        );
+        let strict = Config {
+            strict: true,
+            ..Config::default()
+        };
+
+        let error = install_into(&dir, &strict).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Multiple sources found for hook 'pre-commit'"),
+            "{}",
+            error
+        );
+        let installed = dir.join(".git").join("hooks").join("pre-commit");
+        assert!(!installed.exists());
+
+        let warnings = install_into(&dir, &Config::default()).unwrap();
+        assert!(
+            warnings.iter().any(|warning| warning
+                .contains("Multiple sources found for hook 'pre-commit'")
+                && warning.contains("pre-commit.sh")),
+            "{:?}",
+            warnings
+        );
+        let installed = fs::read_to_string(installed).unwrap();
+        assert!(installed.contains("From pre-commit\""), "{}", installed);
+        assert!(!installed.contains("From pre-commit.sh"), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_labelled_fragments() {
+        let dir = temp_repo(
+            "install-into-fragments",
+            &[
+                (
+                    "pre-commit.test.sh",
+                    "#!/usr/bin/env bash\necho \"test fragment\"\n",
+                ),
+                ("pre-commit.fmt.sh", "#!/bin/sh\necho \"fmt fragment\"\n"),
+            ],
+        );
+
+        install_into(&dir, &Config::default()).unwrap();
+        let installed =
+            fs::read_to_string(dir.join(".git").join("hooks").join("pre-commit")).unwrap();
+        assert!(installed.starts_with("#!/bin/sh\n"), "{}", installed);
+        assert_eq!(installed.matches("#!").count(), 1, "{}", installed);
+        let fmt = installed.find("fmt fragment").unwrap();
+        let test = installed.find("test fragment").unwrap();
+        assert!(fmt < test, "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_only_missing() {
+        let dir = temp_repo(
+            "install-into-only-missing",
+            &[("pre-commit", TEST_HOOK), ("pre-push", TEST_HOOK)],
+        );
+        let hooks_dir = dir.join(".git").join("hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+        let existing = "#!/bin/sh\necho \"existing hook\"\n";
+        fs::write(hooks_dir.join("pre-commit"), existing).unwrap();
+        let config = Config {
+            only_missing: true,
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        assert_eq!(
+            fs::read_to_string(hooks_dir.join("pre-commit")).unwrap(),
+            existing
+        );
+        let installed = fs::read_to_string(hooks_dir.join("pre-push")).unwrap();
+        assert!(installed.contains("This is a test hook"), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_rare_hooks() {
+        let rare_hooks = [
+            "reference-transaction",
+            "fsmonitor-watchman",
+            "post-index-change",
+        ];
+        let hooks: Vec<_> = rare_hooks.iter().map(|hook| (*hook, TEST_HOOK)).collect();
+        let dir = temp_repo("install-into-rare", &hooks);
+
+        install_into(&dir, &Config::default()).unwrap();
+        for hook in rare_hooks {
+            let installed = fs::read_to_string(dir.join(".git").join("hooks").join(hook)).unwrap();
+            assert!(is_managed_hook(&installed), "{}", installed);
+            assert!(installed.contains("This is a test hook"), "{}", installed);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_server_hooks() {
+        let dir = temp_repo(
+            "install-into-server",
+            &[
+                ("pre-commit", TEST_HOOK),
+                ("pre-receive", TEST_HOOK),
+                ("update", TEST_HOOK),
+            ],
+        );
+        let hooks_dir = dir.join(".git").join("hooks");
+
+        install_into(&dir, &Config::default()).unwrap();
+        assert!(hooks_dir.join("pre-commit").exists());
+        assert!(!hooks_dir.join("pre-receive").exists());
+        assert!(!hooks_dir.join("update").exists());
+
+        let config = Config {
+            server_hooks: true,
+            ..Config::default()
+        };
+        install_into(&dir, &config).unwrap();
+        for hook in ["pre-receive", "update"] {
+            let installed = fs::read_to_string(hooks_dir.join(hook)).unwrap();
+            assert!(is_managed_hook(&installed), "{}", installed);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_dev_hooks() {
+        let names = ["pre-commit", "prepare-commit-msg", "commit-msg", "pre-push"];
+        let hooks: Vec<_> = names.iter().map(|name| (*name, TEST_HOOK)).collect();
+        let dir = temp_repo("install-into-dev-hooks", &hooks);
+        let config = Config {
+            dev_hooks: Some(vec!["pre-commit".to_string(), "commit-msg".to_string()]),
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert!(hooks_dir.join("pre-commit").exists());
+        assert!(hooks_dir.join("commit-msg").exists());
+        assert!(!hooks_dir.join("pre-push").exists());
+        assert!(!hooks_dir.join("prepare-commit-msg").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_hooks_src() {
+        let dir = temp_repo("install-into-hooks-src", &[]);
+        fs::create_dir_all(dir.join("githooks")).unwrap();
+        fs::write(dir.join("githooks").join("pre-commit"), TEST_HOOK).unwrap();
+        let config = Config {
+            hooks_src: Some(PathBuf::from("githooks")),
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let installed =
+            fs::read_to_string(dir.join(".git").join("hooks").join("pre-commit")).unwrap();
+        assert!(installed.contains("This is a test hook"), "{}", installed);
+
+        // Reading hooks from where they are installed would overwrite them
+        let config = Config {
+            hooks_src: Some(Path::new(".git").join("hooks")),
+            ..Config::default()
+        };
+        let error = install_into(&dir, &config).unwrap_err();
+        assert!(
+            error.to_string().contains("the same directory"),
+            "{}",
+            error
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_check_interpreters() {
+        let dir = temp_repo(
+            "install-into-interpreters",
+            &[
+                (
+                    "pre-commit",
+                    "#!/usr/bin/env husky-no-such-interpreter\necho \"never runs\"\n",
+                ),
+                ("pre-push", TEST_HOOK),
+            ],
+        );
+        let config = Config {
+            check_interpreters: true,
+            ..Config::default()
+        };
+
+        let warnings = install_into(&dir, &config).unwrap();
+        assert!(
+            warnings.iter().any(|warning| warning.contains(
+                "Hook 'pre-commit' runs with `husky-no-such-interpreter`, which was not found on PATH"
+            )),
+            "{:?}",
+            warnings
+        );
+        assert!(
+            !warnings
+                .iter()
+                .any(|warning| warning.contains("Hook 'pre-push' runs with")),
+            "{:?}",
+            warnings
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_check_syntax() {
+        let dir = temp_repo(
+            "install-into-syntax",
+            &[
+                (
+                    "pre-commit",
+                    "#!/bin/sh\nif true; then\n  echo \"never closed\"\n",
+                ),
+                ("pre-push", TEST_HOOK),
+            ],
+        );
+        let config = Config {
+            check_syntax: true,
+            ..Config::default()
+        };
+        let hooks_dir = dir.join(".git").join("hooks");
+
+        let error = install_into(&dir, &config).unwrap_err();
+        assert!(error.to_string().contains("does not parse"), "{}", error);
+        assert!(!hooks_dir.join("pre-commit").exists());
+        assert!(hooks_dir.join("pre-push").exists());
+
+        fs::write(
+            dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR).join("pre-commit"),
+            "#!/bin/sh\nif true; then\n  echo \"closed\"\nfi\n",
+        )
+        .unwrap();
+        install_into(&dir, &config).unwrap();
+        assert!(hooks_dir.join("pre-commit").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_changed_files_helper() {
+        let dir = temp_repo(
+            "install-into-changed-files",
+            &[(
+                "pre-commit",
+                "#!/bin/sh\nhusky_changed_files -- '*.rs' | while read -r file; do echo \"changed: $file\"; done\n",
+            )],
+        );
+        git(&dir, &["init", "--quiet"]);
+
+        install_into(&dir, &Config::default()).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        let helper =
+            fs::read_to_string(hooks_dir.join(HELPERS_DIR).join(CHANGED_FILES_HELPER)).unwrap();
+        assert!(helper.contains("husky_changed_files()"), "{}", helper);
+
+        fs::write(dir.join("lib.rs"), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        git(&dir, &["add", "lib.rs", "notes.txt"]);
+        let output = run_hook(&hooks_dir.join("pre-commit"), &dir, &[]);
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "changed: lib.rs\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_env_files() {
+        let dir = temp_repo(
+            "install-into-env-files",
+            &[
+                ("pre-commit", TEST_HOOK),
+                ("pre-push", TEST_HOOK),
+                ("pre-commit.env", "OWN=1\n"),
+            ],
+        );
+        let shared_env = dir.join(HUSKY_DIR).join(ENV_FILE);
+        fs::write(&shared_env, "SHARED=1\n").unwrap();
+
+        install_into(&dir, &Config::default()).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        let pre_commit = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+        let shared = format!(". {}", shell_quote(&shared_env.display().to_string()));
+        let own = dir
+            .join(HUSKY_DIR)
+            .join(HUSKY_HOOKS_DIR)
+            .join("pre-commit.env");
+        let own = format!(". {}", shell_quote(&own.display().to_string()));
+        let shared_at = pre_commit
+            .find(&shared)
+            .expect("shared env file is sourced");
+        let own_at = pre_commit.find(&own).expect("per-hook env file is sourced");
+        let body_at = pre_commit.find("This is a test hook").unwrap();
+        assert!(shared_at < own_at && own_at < body_at, "{}", pre_commit);
+
+        let pre_push = fs::read_to_string(hooks_dir.join("pre-push")).unwrap();
+        assert!(pre_push.contains(&shared), "{}", pre_push);
+        assert!(!pre_push.contains("pre-commit.env"), "{}", pre_push);
+        assert!(!hooks_dir.join("pre-commit.env").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_check_mode() {
+        let dir = temp_repo("install-into-check", &[("pre-commit", TEST_HOOK)]);
+        let check = Config {
+            check: true,
+            ..Config::default()
+        };
+        install_into(&dir, &Config::default()).unwrap();
+        install_into(&dir, &check).unwrap();
+
+        let installed_path = dir.join(".git").join("hooks").join("pre-commit");
+        let installed = fs::read_to_string(&installed_path).unwrap();
+        fs::write(
+            dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR).join("pre-commit"),
+            "#!/bin/sh\necho \"Changed hook\"\n",
+        )
+        .unwrap();
+        let error = install_into(&dir, &check).unwrap_err();
+        let HuskyError::OutOfSync(paths) = error else {
+            panic!("{:?}", error);
+        };
+        assert_eq!(paths, vec![installed_path.clone()]);
+        assert_eq!(fs::read_to_string(&installed_path).unwrap(), installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_disabled_hooks() {
+        let dir = temp_repo(
+            "install-into-disabled",
+            &[("pre-commit", TEST_HOOK), ("pre-push", TEST_HOOK)],
+        );
+        fs::write(
+            dir.join(HUSKY_DIR).join(DISABLED_FILE),
+            "# Too slow for every push\npre-push\nnot-a-hook\n",
+        )
+        .unwrap();
+
+        let warnings = install_into(&dir, &Config::default()).unwrap();
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning.contains("Ignoring 'not-a-hook'")),
+            "{:?}",
+            warnings
+        );
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert!(hooks_dir.join("pre-commit").exists());
+        assert!(!hooks_dir.join("pre-push").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_source_comment() {
+        let dir = temp_repo(
+            "install-into-source-comment",
+            &[("pre-commit.sh", TEST_HOOK)],
+        );
+        let config = Config {
+            source_comment: true,
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let installed =
+            fs::read_to_string(dir.join(".git").join("hooks").join("pre-commit")).unwrap();
+        assert!(
+            installed.contains("# source: .husky/hooks/pre-commit.sh\n"),
+            "{}",
+            installed
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_skips_non_files() {
+        let dir = temp_repo("install-into-non-files", &[("pre-push", TEST_HOOK)]);
+        let user_hooks_dir = dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR);
+        fs::create_dir_all(user_hooks_dir.join("pre-commit")).unwrap();
+        fs::write(user_hooks_dir.join("pre-commit").join("lint"), TEST_HOOK).unwrap();
+        #[cfg(unix)]
+        {
+            let status = std::process::Command::new("mkfifo")
+                .arg(user_hooks_dir.join("commit-msg"))
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+
+        let warnings = install_into(&dir, &Config::default()).unwrap();
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning.contains("hooks must be files")),
+            "{:?}",
+            warnings
+        );
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert!(!hooks_dir.join("pre-commit").exists());
+        assert!(hooks_dir.join("pre-push").exists());
+        #[cfg(unix)]
+        {
+            assert!(
+                warnings
+                    .iter()
+                    .any(|warning| warning.contains("hooks must be regular files")),
+                "{:?}",
+                warnings
+            );
+            assert!(!hooks_dir.join("commit-msg").exists());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_ticket_pattern() {
+        let dir = temp_repo("install-into-ticket", &[("pre-commit", TEST_HOOK)]);
+        git(&dir, &["init", "--quiet"]);
+        // An unborn branch still names the ticket
+        git(
+            &dir,
+            &["checkout", "--quiet", "-b", "feature/ABC-123-login"],
+        );
+        let config = Config {
+            ticket_pattern: Some("[A-Z]+-[0-9]+".to_string()),
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert!(hooks_dir.join("pre-commit").exists());
+        let message = dir.join("COMMIT_EDITMSG");
+        let prefix = |args: &[&str]| {
+            fs::write(&message, "Fix login\n").unwrap();
+            let mut hook_args = vec![message.to_str().unwrap()];
+            hook_args.extend(args);
+            let output = run_hook(&hooks_dir.join(TICKET_HOOK), &dir, &hook_args);
+            assert!(output.status.success(), "{:?}", output);
+            fs::read_to_string(&message).unwrap()
+        };
+        assert_eq!(prefix(&[]), "ABC-123: Fix login\n");
+        assert_eq!(prefix(&["message"]), "ABC-123: Fix login\n");
+        assert_eq!(prefix(&["merge"]), "Fix login\n");
+        assert_eq!(prefix(&["squash"]), "Fix login\n");
+
+        git(
+            &dir,
+            &[
+                "-c",
+                "user.name=husky",
+                "-c",
+                "user.email=husky@example.com",
+                "commit",
+                "--quiet",
+                "--allow-empty",
+                "--no-verify",
+                "-m",
+                "init",
+            ],
+        );
+        git(&dir, &["checkout", "--quiet", "--detach"]);
+        fs::remove_dir_all(&hooks_dir).unwrap();
+        install_into(&dir, &config).unwrap();
+        assert!(hooks_dir.join("pre-commit").exists());
+        assert_eq!(prefix(&[]), "Fix login\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_hooks_in_husky_dir() {
+        let dir = temp_repo("install-into-husky-dir", &[]);
+        fs::write(dir.join(HUSKY_DIR).join("pre-commit"), TEST_HOOK).unwrap();
+        let installed = dir.join(".git").join("hooks").join("pre-commit");
+
+        let warnings = install_into(&dir, &Config::default()).unwrap();
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning.contains("hooks belong in")),
+            "{:?}",
+            warnings
+        );
+        assert!(!installed.exists());
+
+        let config = Config {
+            husky_dir_hooks: true,
+            ..Config::default()
+        };
+        install_into(&dir, &config).unwrap();
+        let installed = fs::read_to_string(installed).unwrap();
+        assert!(installed.contains("This is a test hook"), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // The payloads `exec_payload` installed, by path
+    fn payload_files(hooks_dir: &Path) -> Vec<PathBuf> {
+        let mut payloads: Vec<_> = fs::read_dir(hooks_dir.join(PAYLOAD_DIR))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        payloads.sort();
+        payloads
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_exec_payload() {
+        let dir = temp_repo("install-into-exec-payload", &[("pre-commit", TEST_HOOK)]);
+        let config = Config {
+            exec_payload: true,
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        let launcher = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+        assert!(is_managed_hook(&launcher), "{}", launcher);
+        assert!(launcher.contains("exec "), "{}", launcher);
+        assert!(!launcher.contains("This is a test hook"), "{}", launcher);
+        let payloads = payload_files(&hooks_dir);
+        assert_eq!(payloads.len(), 1, "{:?}", payloads);
+        let payload = &payloads[0];
+        assert!(launcher.contains(&*payload.file_name().unwrap().to_string_lossy()));
+        assert_eq!(fs::read_to_string(payload).unwrap(), TEST_HOOK);
+
+        let output = run_hook(&hooks_dir.join("pre-commit"), &dir, &[]);
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "This is a test hook\n"
+        );
+
+        // Installing inline again removes the payload
+        install_into(&dir, &Config::default()).unwrap();
+        assert!(!hooks_dir.join(PAYLOAD_DIR).exists());
+        let installed = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+        assert!(installed.contains("This is a test hook"), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_shared_payload() {
+        let dir = temp_repo(
+            "install-into-shared-payload",
+            &[("pre-commit", TEST_HOOK), ("pre-push", TEST_HOOK)],
+        );
+        let config = Config {
+            exec_payload: true,
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        let payloads = payload_files(&hooks_dir);
+        assert_eq!(payloads.len(), 1, "{:?}", payloads);
+        let hash = payloads[0].file_name().unwrap().to_string_lossy();
+        for hook in ["pre-commit", "pre-push"] {
+            let launcher = fs::read_to_string(hooks_dir.join(hook)).unwrap();
+            assert!(launcher.contains(&*hash), "{}", launcher);
+        }
+
+        // A changed body gets its own payload, the shared one stays for the other hook
+        fs::write(
+            dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR).join("pre-push"),
+            "#!/bin/sh\necho \"pushing\"\n",
+        )
+        .unwrap();
+        install_into(&dir, &config).unwrap();
+        assert_eq!(payload_files(&hooks_dir).len(), 2);
+
+        let clean = Config {
+            clean: true,
+            ..Config::default()
+        };
+        install_into(&dir, &clean).unwrap();
+        assert!(!hooks_dir.join(PAYLOAD_DIR).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_extra_hooks() {
+        let dir = temp_repo(
+            "install-into-extra-hooks",
+            &[("pre-deploy.sh", TEST_HOOK), ("post-deploy", TEST_HOOK)],
+        );
+        let config = Config {
+            extra_hooks: vec!["pre-deploy".to_string()],
+            ..Config::default()
+        };
+
+        let warnings = install_into(&dir, &config).unwrap();
+        assert!(
+            warnings.iter().any(
+                |warning| warning.contains("'pre-deploy' from `extra_hooks` is not a git hook")
+            ),
+            "{:?}",
+            warnings
+        );
+        let hooks_dir = dir.join(".git").join("hooks");
+        let installed = fs::read_to_string(hooks_dir.join("pre-deploy")).unwrap();
+        assert!(installed.contains("This is a test hook"), "{}", installed);
+        assert!(!hooks_dir.join("post-deploy").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_protected_refs() {
+        let dir = temp_repo("install-into-protected-refs", &[]);
+        let config = Config {
+            server_hooks: true,
+            protected_refs: vec!["refs/heads/main".to_string()],
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let hook = dir.join(".git").join("hooks").join(PROTECT_HOOK);
+        let (zero, oid) = ("0".repeat(40), "1".repeat(40));
+        let accepts = |args: &[&str]| run_hook(&hook, &dir, args).status.success();
+        assert!(!accepts(&["refs/heads/main", &oid, &zero]));
+        assert!(accepts(&["refs/heads/topic", &oid, &zero]));
+        assert!(accepts(&["refs/heads/main", &zero, &oid]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_skips_ignored_and_artifacts() {
+        let dir = temp_repo(
+            "install-into-ignored",
+            &[
+                ("pre-commit.sh", TEST_HOOK),
+                // Would otherwise be merged into pre-commit as labelled fragments
+                (
+                    "pre-commit.lib.sh",
+                    "helper() { echo \"shared helper\"; }\n",
+                ),
+                ("pre-commit.sh~", "echo \"stale backup\"\n"),
+                (
+                    IGNORE_FILE,
+                    "# Sourced by hooks, not hooks themselves\n*.lib.sh\n",
+                ),
+                (".DS_Store", "\0\0\0\x01Bud1"),
+                (".gitkeep", ""),
+                (".pre-commit.sh.swp", "swap"),
+                ("pre-push.swp", "echo \"swap file\"\n"),
+            ],
+        );
+
+        let warnings = install_into(&dir, &Config::default()).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let hooks_dir = dir.join(".git").join("hooks");
+        let installed = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+        assert!(installed.contains("This is a test hook"), "{}", installed);
+        assert!(!installed.contains("shared helper"), "{}", installed);
+        assert!(!installed.contains("stale backup"), "{}", installed);
+        assert!(!hooks_dir.join("pre-push").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_clean() {
+        let dir = temp_repo(
+            "install-into-clean",
+            &[
+                ("pre-commit", TEST_HOOK),
+                ("pre-push", "#!/bin/sh\nhusky_changed_files\n"),
+            ],
+        );
+        install_into(&dir, &Config::default()).unwrap();
+
+        let hooks_dir = dir.join(".git").join("hooks");
+        let installed = hooks_dir.join("pre-commit");
+        let content = fs::read_to_string(&installed).unwrap();
+        fs::write(
+            &installed,
+            content.replace(HUSKY_HEADER, "Our team's pre-commit checks"),
+        )
+        .unwrap();
+        fs::write(
+            hooks_dir.join("post-merge"),
+            "#!/bin/sh\necho \"Not ours\"\n",
+        )
+        .unwrap();
+        let config = Config {
+            clean: true,
+            ..Config::default()
+        };
+        install_into(&dir, &config).unwrap();
+
+        // Found by their marker, even with a reworded header
+        assert!(!hooks_dir.join("pre-commit").exists());
+        assert!(!hooks_dir.join("pre-push").exists());
+        assert!(!hooks_dir.join(HELPERS_DIR).exists());
+        assert!(hooks_dir.join("post-merge").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_multiplexed_hooks() {
+        let dir = temp_repo("install-into-multiplexed", &[]);
+        fs::write(
+            dir.join(HUSKY_DIR).join(MULTIPLEXED_FILE),
+            "#!/bin/sh\n\
+             # hook: pre-commit\n\
+             echo \"Checking the commit\"\n\
+             # hook: pre-push\n\
+             echo \"Checking the push\"\n",
+        )
+        .unwrap();
+
+        install_into(&dir, &Config::default()).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        let pre_commit = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+        assert!(pre_commit.starts_with("#!/bin/sh\n"), "{}", pre_commit);
+        assert!(pre_commit.contains("Checking the commit"), "{}", pre_commit);
+        assert!(!pre_commit.contains("Checking the push"), "{}", pre_commit);
+        let pre_push = fs::read_to_string(hooks_dir.join("pre-push")).unwrap();
+        assert!(pre_push.contains("Checking the push"), "{}", pre_push);
+        assert!(!pre_push.contains("Checking the commit"), "{}", pre_push);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_generator() {
+        let dir = temp_repo(
+            "install-into-generator",
+            &[(
+                "pre-commit",
+                "# husky:generate: sh ./scripts/gen-precommit.sh\n",
+            )],
+        );
+        fs::create_dir_all(dir.join("scripts")).unwrap();
+        fs::write(
+            dir.join("scripts").join("gen-precommit.sh"),
+            "printf '#!/bin/sh\\necho \"Generated by policy\"\\n'\n",
+        )
+        .unwrap();
+        let config = Config {
+            generators: true,
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let installed =
+            fs::read_to_string(dir.join(".git").join("hooks").join("pre-commit")).unwrap();
+        assert!(installed.starts_with("#!/bin/sh\n"), "{}", installed);
+        assert!(
+            installed.contains("echo \"Generated by policy\""),
+            "{}",
+            installed
+        );
+        assert!(!installed.contains("husky:generate"), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_owner_fragments() {
+        let dir = temp_repo("install-into-owners", &[]);
+        for owner in ["alpha", "beta"] {
+            let hooks_src = format!("{}-hooks", owner);
+            fs::create_dir_all(dir.join(&hooks_src)).unwrap();
+            fs::write(
+                dir.join(&hooks_src).join("pre-commit"),
+                format!("#!/bin/sh\necho \"{} checks\"\n", owner),
+            )
+            .unwrap();
+            let config = Config {
+                owner: Some(owner.to_string()),
+                hooks_src: Some(PathBuf::from(hooks_src)),
+                ..Config::default()
+            };
+            install_into(&dir, &config).unwrap();
+        }
+
+        let hooks_dir = dir.join(".git").join("hooks");
+        let fragments = hooks_dir.join(format!("pre-commit{}", FRAGMENTS_DIR_SUFFIX));
+        assert!(fragments.join("alpha").is_file());
+        assert!(fragments.join("beta").is_file());
+        let output = run_hook(&hooks_dir.join("pre-commit"), &dir, &[]);
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "alpha checks\nbeta checks\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_on_error() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_repo(
+            "install-into-on-error",
+            &[("pre-commit", "#!/bin/sh\nexit 3\n")],
+        );
+        let mut config = Config {
+            owner: Some("app".to_string()),
+            ..Config::default()
+        };
+        install_into(&dir, &config).unwrap();
+
+        let hooks_dir = dir.join(".git").join("hooks");
+        let marker = dir.join("other-fragment-ran");
+        let other = hooks_dir
+            .join(format!("pre-commit{}", FRAGMENTS_DIR_SUFFIX))
+            .join("zz-other");
+        fs::write(&other, format!("#!/bin/sh\ntouch '{}'\n", marker.display())).unwrap();
+        fs::set_permissions(&other, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let output = run_hook(&hooks_dir.join("pre-commit"), &dir, &[]);
+        assert_eq!(output.status.code(), Some(3), "{:?}", output);
+        assert!(!marker.exists());
+
+        config.on_error.default = OnError::Continue;
+        install_into(&dir, &config).unwrap();
+        let output = run_hook(&hooks_dir.join("pre-commit"), &dir, &[]);
+        assert!(output.status.success(), "{:?}", output);
+        assert!(marker.exists());
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("failed with status 3, continuing")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_review_and_promote() {
+        let dir = temp_repo("install-into-review", &[("pre-commit", TEST_HOOK)]);
+        let mut config = Config {
+            review: true,
+            ..Config::default()
+        };
+        let hooks_dir = dir.join(".git").join("hooks");
+
+        install_into(&dir, &config).unwrap();
+        let staged = fs::read_to_string(hooks_dir.join(REVIEW_DIR).join("pre-commit")).unwrap();
+        assert!(staged.contains("This is a test hook"), "{}", staged);
+        assert!(!hooks_dir.join("pre-commit").exists());
+
+        config.promote = true;
+        install_into(&dir, &config).unwrap();
+        let live = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+        assert_eq!(live, staged);
+
+        // A hook changed since the review stays in staging
+        fs::write(
+            dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR).join("pre-commit"),
+            "#!/bin/sh\necho \"unreviewed\"\n",
+        )
+        .unwrap();
+        let warnings = install_into(&dir, &config).unwrap();
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning.contains("must be reviewed first")),
+            "{:?}",
+            warnings
+        );
+        let live = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+        assert!(!live.contains("unreviewed"), "{}", live);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_gitattributes_eol() {
+        let dir = temp_repo(
+            "install-into-eol",
+            &[
+                (
+                    "pre-commit.py",
+                    "#!/usr/bin/env python3\nprint(\"checking\")\n",
+                ),
+                ("pre-push.sh", TEST_HOOK),
+            ],
+        );
+        fs::write(
+            dir.join(GITATTRIBUTES_FILE),
+            "* text eol=lf\n.husky/hooks/* eol=crlf\n",
+        )
+        .unwrap();
+
+        install_into(&dir, &Config::default()).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        let python = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+        assert!(
+            python.starts_with("#!/usr/bin/env python3\n"),
+            "{:?}",
+            python
+        );
+        assert!(python.contains("print(\"checking\")\r\n"), "{:?}", python);
+        let shell = fs::read_to_string(hooks_dir.join("pre-push")).unwrap();
+        assert!(!shell.contains('\r'), "{:?}", shell);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_export() {
+        let dir = temp_repo(
+            "install-into-export",
+            &[
+                ("pre-commit.sh", TEST_HOOK),
+                ("commit-msg", "echo \"no shebang\"\n"),
+            ],
+        );
+        let config = Config {
+            export: true,
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        let exported = fs::read_to_string(hooks_dir.join("pre-commit.sh")).unwrap();
+        assert!(exported.starts_with("#!/bin/sh\n"), "{}", exported);
+        assert!(is_managed_hook(&exported), "{}", exported);
+        assert!(exported.contains("This is a test hook"), "{}", exported);
+        let exported = fs::read_to_string(hooks_dir.join("commit-msg")).unwrap();
+        assert!(exported.starts_with("#!"), "{}", exported);
+        assert!(!hooks_dir.join("pre-commit").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_opaque_hooks() {
+        let binary: &[u8] = b"\x7fELF\x02\x01\x01\x00\xff\xfe\n\x00binary hook";
+        let dir = temp_repo("install-into-opaque", &[("pre-push", TEST_HOOK)]);
+        fs::write(
+            dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR).join("pre-commit"),
+            binary,
+        )
+        .unwrap();
+        let config = Config {
+            opaque_hooks: vec!["pre-push".to_string()],
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert_eq!(fs::read(hooks_dir.join("pre-commit")).unwrap(), binary);
+        assert_eq!(
+            fs::read_to_string(hooks_dir.join("pre-push")).unwrap(),
+            TEST_HOOK
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for hook in ["pre-commit", "pre-push"] {
+                let mode = fs::metadata(hooks_dir.join(hook))
+                    .unwrap()
+                    .permissions()
+                    .mode();
+                assert_ne!(mode & 0o111, 0, "{} is not executable: {:o}", hook, mode);
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_raw() {
+        let source = "#!/bin/sh\r\n# kept as written\r\necho \"raw hook\"   \r\n\r\n";
+        let dir = temp_repo("install-into-raw", &[("pre-commit", source)]);
+        let config = Config {
+            raw: true,
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let hook = dir.join(".git").join("hooks").join("pre-commit");
+        assert_eq!(fs::read(&hook).unwrap(), source.as_bytes());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&hook).unwrap().permissions().mode();
+            assert_ne!(mode & 0o111, 0, "not executable: {:o}", mode);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_shared_lib() {
+        let dir = temp_repo(
+            "install-into-shared-lib",
+            &[("pre-commit", "#!/bin/sh\ngreet lib\n")],
+        );
+        let lib_dir = dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR).join(LIB_DIR);
+        fs::create_dir_all(&lib_dir).unwrap();
+        fs::write(
+            lib_dir.join("greet.sh"),
+            "greet() { echo \"hello from $1\"; }\n",
+        )
+        .unwrap();
+
+        let warnings = install_into(&dir, &Config::default()).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert!(hooks_dir.join(LIB_DIR).join("greet.sh").is_file());
+        let output = run_hook(&hooks_dir.join("pre-commit"), &dir, &[]);
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hello from lib\n");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_required_hooks() {
+        let dir = temp_repo("install-into-required", &[("pre-commit", TEST_HOOK)]);
+        let config = Config {
+            required_hooks: ["pre-commit", "pre-push", "post-merge"]
+                .map(String::from)
+                .to_vec(),
+            ..Config::default()
+        };
+
+        let error = install_into(&dir, &config).unwrap_err();
+        let message = error.to_string();
+        assert!(
+            message.contains("Hooks listed in `required_hooks` have no source in")
+                && message.contains(": pre-push, post-merge"),
+            "{}",
+            message
+        );
+
+        let user_hooks_dir = dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR);
+        fs::write(user_hooks_dir.join("pre-push"), TEST_HOOK).unwrap();
+        fs::write(user_hooks_dir.join("post-merge"), TEST_HOOK).unwrap();
+        install_into(&dir, &config).unwrap();
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert!(hooks_dir.join("pre-push").is_file());
+        assert!(hooks_dir.join("post-merge").is_file());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_into_unreadable_source() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_repo(
+            "install-into-unreadable",
+            &[("pre-commit", TEST_HOOK), ("pre-push", TEST_HOOK)],
+        );
+        let source = dir.join(HUSKY_DIR).join(HUSKY_HOOKS_DIR).join("pre-push");
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions don't apply to root
+        if fs::read(&source).is_ok() {
+            fs::remove_dir_all(&dir).unwrap();
+            return;
+        }
+
+        let warnings = install_into(&dir, &Config::default()).unwrap();
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning
+                    .contains("cannot be read, check its permissions, skipping it")),
+            "{:?}",
+            warnings
+        );
+        let hooks_dir = dir.join(".git").join("hooks");
+        assert!(hooks_dir.join("pre-commit").is_file());
+        assert!(!hooks_dir.join("pre-push").exists());
+
+        let strict = Config {
+            strict: true,
+            ..Config::default()
+        };
+        let error = install_into(&dir, &strict).unwrap_err();
+        assert!(error.to_string().contains("cannot be read"), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_private_temp_dir() {
+        let first = private_temp_dir().unwrap();
+        let second = private_temp_dir().unwrap();
+        assert_ne!(first, second);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        fs::remove_dir(&first).unwrap();
+        fs::remove_dir(&second).unwrap();
+
+        // Without an `OUT_DIR`, a session creates its own and removes it when done
+        let (scratch, session) = Session::new(false, None).run(|| scratch_dir().unwrap());
+        assert!(scratch.is_dir());
+        drop(session);
+        assert!(!scratch.exists());
+    }
+
+    #[test]
+    fn test_install_into_generated_hooks_leave_no_scratch() {
+        let dir = temp_repo("install-into-scratch", &[]);
+        let config = Config {
+            ticket_pattern: Some("[A-Z]+-[0-9]+".to_string()),
+            ..Config::default()
+        };
+
+        let (warnings, session) =
+            Session::new(false, None).run(|| install_into(&dir, &config).unwrap());
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        // The outer session saw nothing of the install
+        assert!(session.temp_dir.is_none());
+        assert!(session.finish().0.is_empty());
+        let installed = dir.join(".git").join("hooks").join(TICKET_HOOK);
+        let content = fs::read_to_string(&installed).unwrap();
+        assert!(
+            content.contains("# source: husky-generated-hooks/prepare-commit-msg\n"),
+            "{}",
+            content
+        );
+        // Named the same every time, the hook is left alone on the next install
+        let modified = fs::metadata(&installed).unwrap().modified().unwrap();
+        install_into(&dir, &config).unwrap();
+        assert_eq!(
+            fs::metadata(&installed).unwrap().modified().unwrap(),
+            modified
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_report() {
+        let dir = temp_repo(
+            "install-into-report",
+            &[("pre-commit", TEST_HOOK), ("pre-push", TEST_HOOK)],
+        );
+        fs::write(dir.join(HUSKY_DIR).join(DISABLED_FILE), "pre-push\n").unwrap();
+        let config = Config {
+            report: true,
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let report = dir.join(".git").join(REPORT_FILE);
+        let report = fs::read_to_string(report).unwrap();
+        assert!(
+            report.contains(&format!("git dir: {}", dir.join(".git").display())),
+            "{}",
+            report
+        );
+        assert!(
+            report
+                .lines()
+                .any(|line| line.trim_start().starts_with("pre-commit")
+                    && line.ends_with("installed")),
+            "{}",
+            report
+        );
+        assert!(report.contains("skipped, disabled"), "{}", report);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_ignored_hooks_dir() {
+        let dir = temp_repo("install-into-ignored-dir", &[("pre-commit", TEST_HOOK)]);
+        git(&dir, &["init", "--quiet"]);
+        fs::write(dir.join(".gitignore"), ".husky\n").unwrap();
+        let is_ignored_warning = |warning: &String| warning.contains("is ignored by git");
+
+        let config = Config {
+            check_ignored: false,
+            ..Config::default()
+        };
+        let warnings = install_into(&dir, &config).unwrap();
+        assert!(!warnings.iter().any(is_ignored_warning), "{:?}", warnings);
+
+        let warnings = install_into(&dir, &Config::default()).unwrap();
+        assert!(warnings.iter().any(is_ignored_warning), "{:?}", warnings);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_into_fmt_check() {
+        let dir = temp_repo("install-into-fmt-check", &[]);
+        let config = Config {
+            fmt_check: Some(vec!["--".to_string(), "--check".to_string()]),
+            ..Config::default()
+        };
+
+        install_into(&dir, &config).unwrap();
+        let installed = fs::read_to_string(dir.join(".git").join("hooks").join(FMT_HOOK)).unwrap();
+        assert!(is_managed_hook(&installed), "{}", installed);
+        assert!(installed.contains("cargo fmt -- --check"), "{}", installed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
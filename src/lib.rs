@@ -1 +1,84 @@
+//! Installs the git hooks in `.husky/hooks` when built as a dependency. The build script does that
+//! by itself; [`install_into`] does the same for a repository of the caller's choosing.
 
+use std::path::{Path, PathBuf};
+
+// Shared with the build script
+#[macro_use]
+mod install;
+// Only built here for its tests, the build script includes it to run it
+#[cfg(test)]
+mod build_script;
+
+use install::{
+    check_git_dir_readable, common_git_dir, discover_git_dir, install_hooks_with, parse_table,
+    HookTarget, ResolvedTarget, Result, Session, Settings,
+};
+pub use install::{Config, HuskyError, CI_ENV_VARS, SERVER_HOOKS, VALID_HOOK_NAMES};
+
+/// The hooks of [`VALID_HOOK_NAMES`] that run in a repository people work in, as git's
+/// documentation groups them: all but [`SERVER_HOOKS`].
+pub const CLIENT_HOOKS: [&str; 22] = [
+    "applypatch-msg",
+    "pre-applypatch",
+    "post-applypatch",
+    "pre-commit",
+    "pre-merge-commit",
+    "prepare-commit-msg",
+    "commit-msg",
+    "post-commit",
+    "pre-rebase",
+    "post-checkout",
+    "post-merge",
+    "pre-push",
+    "reference-transaction",
+    "pre-auto-gc",
+    "post-rewrite",
+    "sendemail-validate",
+    "fsmonitor-watchman",
+    "p4-changelist",
+    "p4-prepare-changelist",
+    "p4-post-changelist",
+    "p4-pre-submit",
+    "post-index-change",
+];
+
+impl Config {
+    /// Reads settings written as the body of a `[package.metadata.husky]` table, e.g.
+    /// `"raw = true\nrequired_hooks = [\"pre-commit\"]"`. Unlike [`Config::load`], it ignores
+    /// the environment, so switches the build script takes from it are settings here:
+    /// `check`, `clean`, `list`, `promote` and `strict` for `HUSKY_<KEY>`, and `export` for
+    /// `HUSKY_EXPORT_DIR`.
+    pub fn from_metadata(settings: &str) -> Result<Self> {
+        let table = parse_table(settings, None).map_err(HuskyError::Config)?;
+        Config::from_settings(&Settings { table, env: false }, None)
+    }
+}
+
+// A repository named outright, for tools installing hooks somewhere other than the build's own
+// project. A linked worktree's hooks live in the git dir it shares with the main worktree.
+struct RepoTarget(PathBuf);
+
+impl HookTarget for RepoTarget {
+    fn resolve(&self) -> Result<ResolvedTarget> {
+        let not_found = || HuskyError::GitDirNotFound(self.0.display().to_string());
+        let (git_dir, project_root) = discover_git_dir(&self.0)?.ok_or_else(not_found)?;
+        let git_dir = common_git_dir(git_dir)?;
+        check_git_dir_readable(&git_dir)?;
+        Ok(ResolvedTarget {
+            hooks_dir: git_dir.join("hooks"),
+            repo_dir: git_dir,
+            project_root,
+        })
+    }
+}
+
+/// Installs the hooks of the git repository at `repo_path` (its work tree, or a bare repository),
+/// wherever the current directory is, as configured by `config`. Nothing is printed: the warnings
+/// a build would show are returned instead.
+pub fn install_into(repo_path: &Path, config: &Config) -> Result<Vec<String>> {
+    let resolved = RepoTarget(repo_path.to_path_buf()).resolve()?;
+    let (result, session) =
+        Session::new(false, None).run(|| install_hooks_with(resolved, config.clone()));
+    result.map(|()| session.finish().0)
+}
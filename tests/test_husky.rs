@@ -2,9 +2,11 @@ use std::env;
 use std::fs::{self};
 use std::io::Error;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Output};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use husky_rs::CI_ENV_VARS;
+
 const HOOK_TYPES: &[&str] = &["pre-commit", "prepare-commit-msg", "commit-msg", "pre-push"];
 const HOOK_TEMPLATE: &str = "#!/bin/sh\necho \"This is a test hook\"\n";
 
@@ -51,17 +53,26 @@ struct TestProject {
 impl TestProject {
     // Creates a new test project in a temporary directory
     fn new(prefix: &str) -> Result<Self, Error> {
+        Self::with_vcs(prefix, "git")
+    }
+
+    // Creates a new test project in a temporary directory that is not a git repository
+    fn new_without_git(prefix: &str) -> Result<Self, Error> {
+        Self::with_vcs(prefix, "none")
+    }
+
+    fn with_vcs(prefix: &str, vcs: &str) -> Result<Self, Error> {
         let project = TestProject {
             path: create_temp_dir(prefix)?,
         };
-        project.init()?;
+        project.init(vcs)?;
         Ok(project)
     }
 
     // Initializes a new cargo project in the test directory
-    fn init(&self) -> Result<(), Error> {
+    fn init(&self, vcs: &str) -> Result<(), Error> {
         Command::new("cargo")
-            .args(["init", "--bin"])
+            .args(["init", "--bin", "--vcs", vcs])
             .current_dir(&self.path)
             .status()?;
         Ok(())
@@ -69,13 +80,25 @@ impl TestProject {
 
     // Adds husky-rs to the Cargo.toml file, depending on whether it is a regular or dev dependency
     fn add_husky_rs_to_toml(&self, dependencies_type: &str) -> Result<(), Error> {
+        self.add_husky_rs_with_features(dependencies_type, &[])
+    }
+
+    // Adds husky-rs to the Cargo.toml file with the given features enabled
+    fn add_husky_rs_with_features(
+        &self,
+        dependencies_type: &str,
+        features: &[&str],
+    ) -> Result<(), Error> {
         let cargo_toml_path = self.path.join("Cargo.toml");
         let mut cargo_toml = fs::read_to_string(&cargo_toml_path)?;
         let current_crate_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         let relative_crate_path = get_relative_path(&self.path, &current_crate_path);
 
         let section = format!("[{}]", dependencies_type);
-        let husky_rs_dep = format!("husky-rs = {{ path = {:?} }}\n", relative_crate_path);
+        let husky_rs_dep = format!(
+            "husky-rs = {{ path = {:?}, features = {:?} }}\n",
+            relative_crate_path, features
+        );
 
         // Insert husky-rs into the correct section of Cargo.toml
         if let Some(pos) = cargo_toml.find(&section) {
@@ -92,6 +115,14 @@ impl TestProject {
         Ok(())
     }
 
+    // Appends husky-rs settings to the [package.metadata.husky] table of Cargo.toml
+    fn add_husky_metadata(&self, settings: &str) -> Result<(), Error> {
+        let cargo_toml_path = self.path.join("Cargo.toml");
+        let mut cargo_toml = fs::read_to_string(&cargo_toml_path)?;
+        cargo_toml.push_str(&format!("\n[package.metadata.husky]\n{}\n", settings));
+        fs::write(&cargo_toml_path, cargo_toml)
+    }
+
     // Creates Husky Git hooks with the given content
     fn create_hooks(&self) -> Result<(), Error> {
         let husky_dir = self.path.join(".husky").join("hooks");
@@ -103,18 +134,97 @@ impl TestProject {
         Ok(())
     }
 
+    // Creates a single Husky Git hook with the given file name and content
+    fn create_hook(&self, file_name: &str, content: &str) -> Result<(), Error> {
+        let husky_dir = self.path.join(".husky").join("hooks");
+        fs::create_dir_all(&husky_dir)?;
+        fs::write(husky_dir.join(file_name), content)
+    }
+
     // Runs a cargo command (e.g., build, test, clean) in the project directory
     fn run_cargo_command(&self, command: &str) -> Result<(), Error> {
-        Command::new("cargo")
-            .arg(command)
+        self.run_cargo_command_with_env(command, &[])
+    }
+
+    // Runs a cargo command with extra environment variables set for the build
+    fn run_cargo_command_with_env(
+        &self,
+        command: &str,
+        envs: &[(&str, &str)],
+    ) -> Result<(), Error> {
+        self.cargo_command(&[command], envs).status()?;
+        Ok(())
+    }
+
+    // Runs a cargo command verbosely, capturing its output (including build script output)
+    fn run_cargo_command_with_output(
+        &self,
+        command: &str,
+        envs: &[(&str, &str)],
+    ) -> Result<Output, Error> {
+        self.cargo_command(&[command, "-vv"], envs).output()
+    }
+
+    fn cargo_command(&self, args: &[&str], envs: &[(&str, &str)]) -> Command {
+        let mut command = Command::new("cargo");
+        command
+            .args(args)
             .current_dir(&self.path)
+            // Keep the git environment of the outer process from leaking into the build
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_COMMON_DIR")
+            .envs(envs.iter().copied());
+        command
+    }
+
+    // Reads the output recorded for the husky-rs build script run
+    fn build_script_output(&self) -> Result<String, Error> {
+        self.read_build_file(Path::new("output"))
+    }
+
+    // Reads a file from the husky-rs build script's build directory, e.g. `out/<name>`
+    fn read_build_file(&self, relative: &Path) -> Result<String, Error> {
+        fs::read_to_string(self.build_file_path(relative)?)
+    }
+
+    // Finds a file the build script left in its build directory
+    fn build_file_path(&self, relative: &Path) -> Result<PathBuf, Error> {
+        let build_dir = self.path.join("target").join("debug").join("build");
+        for entry in fs::read_dir(build_dir)? {
+            let dir = entry?.path();
+            let is_husky = dir
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("husky-rs-"));
+            if is_husky && dir.join(relative).is_file() {
+                return Ok(dir.join(relative));
+            }
+        }
+        Err(Error::other(format!(
+            "husky-rs build file '{}' not found",
+            relative.display()
+        )))
+    }
+
+    // Runs a git command in the project directory
+    fn run_git_command(&self, args: &[&str]) -> Result<(), Error> {
+        Command::new("git")
+            .args(args)
+            .current_dir(&self.path)
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_COMMON_DIR")
             .status()?;
         Ok(())
     }
 
     // Verifies the existence and content of Git hooks
     fn verify_hooks(&self, expect_hooks: bool) -> Result<(), Error> {
-        let git_hooks_dir = self.path.join(".git").join("hooks");
+        self.verify_hooks_in(&self.path.join(".git").join("hooks"), expect_hooks)
+    }
+
+    // Verifies the existence and content of Git hooks in the given hooks directory
+    fn verify_hooks_in(&self, git_hooks_dir: &Path, expect_hooks: bool) -> Result<(), Error> {
         for hook in HOOK_TYPES {
             let hook_path = git_hooks_dir.join(hook);
             let hook_exists = hook_path.exists();
@@ -162,7 +272,23 @@ fn test_husky_rs_with_dependencies() -> Result<(), Error> {
     project.add_husky_rs_to_toml("dependencies")?;
     project.create_hooks()?;
     project.run_cargo_command("build")?;
-    project.verify_hooks(true)
+    project.verify_hooks(true)?;
+    // The install is reported to dependents as metadata
+    let output = project.build_script_output()?;
+    assert!(
+        output.contains(&format!(
+            "cargo::metadata=hooks_installed={}\n",
+            HOOK_TYPES.len()
+        )),
+        "Missing hook count in: {}",
+        output
+    );
+    assert!(
+        output.contains("cargo::metadata=git_dir="),
+        "Missing git directory in: {}",
+        output
+    );
+    Ok(())
 }
 
 // Test: Verify husky-rs works as a dev dependency with cargo test
@@ -185,6 +311,41 @@ fn test_husky_rs_with_dev_dependencies_and_cargo_build() -> Result<(), Error> {
     project.verify_hooks(false)
 }
 
+// Test: Verify cargo check and cargo run install hooks too, as they run build scripts
+#[test]
+fn test_husky_rs_with_dependencies_and_cargo_check_or_run() -> Result<(), Error> {
+    for command in ["check", "run"] {
+        let project = TestProject::new(&format!("husky-rs-dep-{}-test-", command))?;
+        project.add_husky_rs_to_toml("dependencies")?;
+        project.create_hooks()?;
+        project.run_cargo_command(command)?;
+        project.verify_hooks(true)?;
+    }
+    Ok(())
+}
+
+// Test: Verify cargo check only installs hooks from a dev dependency when it checks tests
+#[test]
+fn test_husky_rs_with_dev_dependencies_and_cargo_check() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-dev-dep-check-test-")?;
+    project.add_husky_rs_to_toml("dev-dependencies")?;
+    project.create_hooks()?;
+    project.run_cargo_command("check")?;
+    project.verify_hooks(false)?;
+    project.cargo_command(&["check", "--tests"], &[]).status()?;
+    project.verify_hooks(true)
+}
+
+// Test: Verify cargo run installs no hooks from a dev dependency
+#[test]
+fn test_husky_rs_with_dev_dependencies_and_cargo_run() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-dev-dep-run-test-")?;
+    project.add_husky_rs_to_toml("dev-dependencies")?;
+    project.create_hooks()?;
+    project.run_cargo_command("run")?;
+    project.verify_hooks(false)
+}
+
 // Test: Verify husky-rs works correctly after a cargo clean
 #[test]
 fn test_husky_rs_after_cargo_clean() -> Result<(), Error> {
@@ -196,3 +357,717 @@ fn test_husky_rs_after_cargo_clean() -> Result<(), Error> {
     project.run_cargo_command("build")?;
     project.verify_hooks(true)
 }
+
+// Test: Verify hooks are installed into the directory given by GIT_COMMON_DIR
+#[test]
+fn test_husky_rs_with_git_common_dir() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-common-dir-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    let common_dir = project.path.join("common.git");
+    fs::create_dir_all(&common_dir)?;
+    project
+        .run_cargo_command_with_env("build", &[("GIT_COMMON_DIR", common_dir.to_str().unwrap())])?;
+    project.verify_hooks_in(&common_dir.join("hooks"), true)?;
+    project.verify_hooks(false)
+}
+
+// Test: Verify building a linked worktree installs its hooks into the git dir it shares with the main worktree
+#[test]
+fn test_husky_rs_in_linked_worktree() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-worktree-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    project.run_git_command(&["add", "--all"])?;
+    project.run_git_command(&[
+        "-c",
+        "user.name=husky",
+        "-c",
+        "user.email=husky@example.com",
+        "commit",
+        "--quiet",
+        "--no-verify",
+        "-m",
+        "init",
+    ])?;
+    // A sibling, so the relative path to husky-rs in Cargo.toml still resolves
+    let linked = TestProject {
+        path: project.path.with_extension("linked"),
+    };
+    project.run_git_command(&["worktree", "add", "--quiet", linked.path.to_str().unwrap()])?;
+    // Only the linked worktree has sources left, so hooks can only come from there
+    fs::remove_dir_all(project.path.join(".husky"))?;
+
+    // Git names the worktree's own git dir after the worktree
+    let git_dir = project
+        .path
+        .join(".git")
+        .join("worktrees")
+        .join(linked.path.file_name().unwrap());
+
+    linked.run_cargo_command("build")?;
+    project.verify_hooks(true)?;
+    assert!(!git_dir.join("hooks").exists());
+
+    // As in a hook run from the worktree, with a GIT_COMMON_DIR that leads nowhere
+    fs::remove_dir_all(project.path.join(".git").join("hooks"))?;
+    linked.run_cargo_command_with_env(
+        "build",
+        &[
+            ("GIT_DIR", git_dir.to_str().unwrap()),
+            ("GIT_COMMON_DIR", "missing.git"),
+        ],
+    )?;
+    project.verify_hooks(true)?;
+    fs::remove_dir_all(&linked.path)
+}
+
+// Test: Verify building outside a git repository does not report an installation error
+#[test]
+fn test_husky_rs_without_git_repository() -> Result<(), Error> {
+    let project = TestProject::new_without_git("husky-rs-no-git-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    let output = project.run_cargo_command_with_output("build", &[])?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "Build failed: {}", stderr);
+    assert!(
+        !stderr.contains("Error during hook installation"),
+        "Unexpected installation error reported: {}",
+        stderr
+    );
+    Ok(())
+}
+
+// Test: Verify a split GIT_DIR / GIT_WORK_TREE layout reads from the work tree and writes to the git dir
+#[test]
+fn test_husky_rs_with_split_work_tree() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-work-tree-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    let git_dir = project.path.join("meta").join("repo.git");
+    fs::create_dir_all(&git_dir)?;
+    project.run_cargo_command_with_env(
+        "build",
+        &[
+            ("GIT_DIR", git_dir.to_str().unwrap()),
+            ("GIT_WORK_TREE", project.path.to_str().unwrap()),
+        ],
+    )?;
+    project.verify_hooks_in(&git_dir.join("hooks"), true)?;
+    project.verify_hooks(false)
+}
+
+// Test: Verify HUSKY_LOG controls how much husky-rs reports about a successful install
+#[test]
+fn test_husky_rs_log_levels() -> Result<(), Error> {
+    // (level, expect summary line, expect per-hook lines)
+    let cases = [
+        ("silent", false, false),
+        ("error", false, false),
+        ("info", true, false),
+        ("debug", true, true),
+    ];
+    for (level, expect_summary, expect_details) in cases {
+        let project = TestProject::new(&format!("husky-rs-log-{}-test-", level))?;
+        project.add_husky_rs_to_toml("dependencies")?;
+        project.create_hooks()?;
+        let output = project.run_cargo_command_with_output("build", &[("HUSKY_LOG", level)])?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "Build failed at level {}", level);
+        assert_eq!(
+            stdout.contains("Installed 4 hook(s)"),
+            expect_summary,
+            "Unexpected summary output at level {}: {}",
+            level,
+            stdout
+        );
+        assert_eq!(
+            stdout.contains("Installing hook"),
+            expect_details,
+            "Unexpected per-hook output at level {}: {}",
+            level,
+            stdout
+        );
+        project.verify_hooks(true)?;
+    }
+    Ok(())
+}
+
+// Test: Verify HUSKY_LOG=silent also hides installation errors while still failing the build
+#[test]
+fn test_husky_rs_silent_log_level_hides_errors() -> Result<(), Error> {
+    for (level, expect_error) in [("silent", false), ("error", true)] {
+        let project = TestProject::new(&format!("husky-rs-log-error-{}-test-", level))?;
+        project.add_husky_rs_to_toml("dependencies")?;
+        project.create_hooks()?;
+        // A directory in place of the installed hook makes writing it fail
+        fs::create_dir_all(project.path.join(".git").join("hooks").join("pre-commit"))?;
+        let output = project.run_cargo_command_with_output("build", &[("HUSKY_LOG", level)])?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "Build unexpectedly succeeded");
+        assert_eq!(
+            stderr.contains("Error during hook installation"),
+            expect_error,
+            "Unexpected error output at level {}: {}",
+            level,
+            stderr
+        );
+    }
+    Ok(())
+}
+
+// Test: Verify hooks are reinstalled after the git directory is removed and re-initialized
+#[test]
+fn test_husky_rs_after_git_reinit() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-reinit-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    project.run_cargo_command("build")?;
+    project.verify_hooks(true)?;
+    fs::remove_dir_all(project.path.join(".git"))?;
+    project.run_git_command(&["init", "--quiet"])?;
+    project.run_cargo_command("build")?;
+    project.verify_hooks(true)
+}
+
+// Test: Verify HUSKY_STRICT turns a missing git repository into a build failure
+#[test]
+fn test_husky_rs_strict_without_git_repository() -> Result<(), Error> {
+    let project = TestProject::new_without_git("husky-rs-strict-no-git-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    let output = project.run_cargo_command_with_output("build", &[("HUSKY_STRICT", "1")])?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "Build unexpectedly succeeded");
+    assert!(
+        stderr.contains("Git directory not found"),
+        "Missing git directory error: {}",
+        stderr
+    );
+    Ok(())
+}
+
+// Test: Verify `skip_in_ci` installs nothing while a CI variable is set
+#[test]
+fn test_husky_rs_skip_in_ci() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-skip-in-ci-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.add_husky_metadata("skip_in_ci = true")?;
+    project.create_hooks()?;
+
+    // Whatever CI the tests themselves run in, only the variables given here count
+    let build = |envs: &[(&str, &str)]| {
+        let mut command = project.cargo_command(&["build"], &[]);
+        for var in CI_ENV_VARS {
+            command.env_remove(var);
+        }
+        command.envs(envs.iter().copied()).status()
+    };
+    build(&[("CI", "true")])?;
+    project.verify_hooks(false)?;
+    let output = project.build_script_output()?;
+    assert!(
+        output.contains("CI is set, skipping hook installation in CI"),
+        "{}",
+        output
+    );
+
+    build(&[])?;
+    project.verify_hooks(true)
+}
+
+// Test: Verify a header-only change reuses the bodies read by the last build, until a source changes
+#[test]
+fn test_husky_rs_read_cache() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-read-cache-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    project.run_cargo_command("build")?;
+
+    let envs = [("HUSKY_HEADER_VERSION", "0.0.1"), ("HUSKY_LOG", "debug")];
+    project.run_cargo_command_with_env("build", &envs)?;
+    let output = project.build_script_output()?;
+    assert!(
+        output.contains("Reusing the body of hook 'pre-commit'"),
+        "Sources read again in: {}",
+        output
+    );
+    let hook_path = project.path.join(".git").join("hooks").join("pre-commit");
+    let installed = fs::read_to_string(&hook_path)?;
+    assert!(installed.contains("v0.0.1: "), "{}", installed);
+    assert!(installed.contains("This is a test hook"), "{}", installed);
+
+    project.create_hook("pre-commit", "#!/bin/sh\necho \"edited since\"\n")?;
+    project.run_cargo_command_with_env("build", &envs)?;
+    let installed = fs::read_to_string(&hook_path)?;
+    assert!(installed.contains("edited since"), "{}", installed);
+    Ok(())
+}
+
+// Test: Verify `profiles` limits installation to the listed Cargo profiles
+#[test]
+fn test_husky_rs_profiles() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-profiles-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.add_husky_metadata("profiles = [\"debug\"]")?;
+    project.create_hooks()?;
+
+    project
+        .cargo_command(&["build", "--release"], &[])
+        .status()?;
+    project.verify_hooks(false)?;
+
+    project.run_cargo_command("build")?;
+    project.verify_hooks(true)?;
+    let output = project.build_script_output()?;
+    assert!(
+        output.contains("cargo::metadata=profile=debug\n"),
+        "Missing profile in: {}",
+        output
+    );
+    Ok(())
+}
+
+// Test: Verify NO_HUSKY_HOOKS wins over HUSKY_FORCE, with a warning about the conflict
+#[test]
+fn test_husky_rs_disabled_and_forced() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-disabled-forced-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+
+    let output = project
+        .run_cargo_command_with_output("build", &[("NO_HUSKY_HOOKS", "1"), ("HUSKY_FORCE", "1")])?;
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("NO_HUSKY_HOOKS takes precedence"),
+        "Missing conflict warning in: {}",
+        stderr
+    );
+    project.verify_hooks(false)?;
+
+    project.run_cargo_command_with_env("build", &[("HUSKY_FORCE", "1")])?;
+    project.verify_hooks(true)
+}
+
+// Test: Verify the built-in fmt pre-commit hook runs cargo fmt and fails on unformatted code
+#[test]
+fn test_husky_rs_fmt_check() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-fmt-check-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.add_husky_metadata("fmt_check = true\nfmt_args = [\"--\", \"--check\"]")?;
+    project.run_cargo_command("build")?;
+
+    let hook = project.path.join(".git").join("hooks").join("pre-commit");
+    let installed = fs::read_to_string(&hook)?;
+    assert!(
+        installed.contains("cargo fmt -- --check"),
+        "Missing fmt invocation in: {}",
+        installed
+    );
+
+    let run_hook = || {
+        Command::new(&hook)
+            .current_dir(&project.path)
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_COMMON_DIR")
+            .output()
+    };
+    let output = run_hook()?;
+    assert!(output.status.success(), "{:?}", output);
+
+    fs::write(
+        project.path.join("src").join("main.rs"),
+        "fn main(){println!(\"unformatted\");}\n",
+    )?;
+    let output = run_hook()?;
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("code is not formatted"));
+    Ok(())
+}
+
+// Test: Verify HUSKY_TIMING reports how long each phase took, and nothing without it
+#[test]
+fn test_husky_rs_timing() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-timing-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+
+    let output = project.run_cargo_command_with_output("build", &[])?;
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("husky-rs timing"), "{}", stderr);
+
+    let output = project.run_cargo_command_with_output("build", &[("HUSKY_TIMING", "1")])?;
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for phase in ["git dir discovery", "reading", "writing"] {
+        assert!(
+            stderr.contains(&format!("husky-rs timing: {} took", phase)),
+            "Missing {} timing in: {}",
+            phase,
+            stderr
+        );
+    }
+    Ok(())
+}
+
+// Test: Verify HUSKY_LIST enumerates the installed hooks with their version and source
+#[test]
+fn test_husky_rs_list() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-list-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    project.run_cargo_command("build")?;
+
+    let output = project.run_cargo_command_with_output("build", &[("HUSKY_LIST", "1")])?;
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for hook in HOOK_TYPES {
+        let entry = format!(
+            "husky-rs list: {} v{} from .husky/hooks/{}",
+            hook,
+            env!("CARGO_PKG_VERSION"),
+            hook
+        );
+        assert!(
+            stderr.contains(&entry),
+            "Missing '{}' in: {}",
+            entry,
+            stderr
+        );
+    }
+    Ok(())
+}
+
+// Test: Verify hooks are staged in .husky/.staged-hooks outside a git repository when opted in
+#[test]
+fn test_husky_rs_stage_without_git() -> Result<(), Error> {
+    let project = TestProject::new_without_git("husky-rs-stage-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    let staged = project.path.join(".husky").join(".staged-hooks");
+
+    project.run_cargo_command("build")?;
+    assert!(!staged.exists(), "Hooks staged without opting in");
+
+    project.add_husky_metadata("stage_without_git = true")?;
+    project.run_cargo_command("build")?;
+    for hook in HOOK_TYPES {
+        let content = fs::read_to_string(staged.join(hook))?;
+        assert!(
+            content.contains("This hook was set by husky-rs"),
+            "{}",
+            content
+        );
+        assert!(content.contains("This is a test hook"), "{}", content);
+    }
+    Ok(())
+}
+
+// Test: Verify a strict build outside a git repository reports the documented exit status
+#[test]
+fn test_husky_rs_exit_code_without_git() -> Result<(), Error> {
+    let project = TestProject::new_without_git("husky-rs-exit-code-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    let output = project.run_cargo_command_with_output("build", &[("HUSKY_STRICT", "1")])?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // cargo's own status is the same for every build script failure, only its message differs
+    assert_eq!(output.status.code(), Some(101), "{}", stderr);
+    assert!(
+        stderr.contains("exit status: 2"),
+        "Missing exit status 2 in: {}",
+        stderr
+    );
+    Ok(())
+}
+
+// Test: Verify `HUSKY_DEST_DIR` installs hooks into the given directory without a git repository
+#[test]
+fn test_husky_rs_dest_dir() -> Result<(), Error> {
+    let project = TestProject::new_without_git("husky-rs-dest-dir-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    let dest_dir = create_temp_dir("husky-rs-dest-dir-hooks-")?;
+
+    let output =
+        project.run_cargo_command_with_output("build", &[("HUSKY_DEST_DIR", "does-not-exist")])?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "Build unexpectedly succeeded");
+    assert!(
+        stderr.contains("is not an existing directory"),
+        "{}",
+        stderr
+    );
+
+    project
+        .run_cargo_command_with_env("build", &[("HUSKY_DEST_DIR", dest_dir.to_str().unwrap())])?;
+    for hook in HOOK_TYPES {
+        let content = fs::read_to_string(dest_dir.join(hook))?;
+        assert!(
+            content.contains("This hook was set by husky-rs"),
+            "{}",
+            content
+        );
+        assert!(content.contains("This is a test hook"), "{}", content);
+    }
+    assert!(!project.path.join(".husky").join(".staged-hooks").exists());
+    fs::remove_dir_all(&dest_dir)?;
+    Ok(())
+}
+
+// Test: Verify `HUSKY_EXPORT_DIR` writes processed hooks under their source names, leaving .git/hooks alone
+#[test]
+fn test_husky_rs_export_dir() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-export-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hook("pre-commit.sh", HOOK_TEMPLATE)?;
+    project.create_hook("commit-msg", "echo \"no shebang\"\n")?;
+    let export_dir = create_temp_dir("husky-rs-export-hooks-")?;
+
+    project.run_cargo_command_with_env(
+        "build",
+        &[("HUSKY_EXPORT_DIR", export_dir.to_str().unwrap())],
+    )?;
+    let exported = fs::read_to_string(export_dir.join("pre-commit.sh"))?;
+    assert!(exported.starts_with("#!/bin/sh\n"), "{}", exported);
+    assert!(
+        exported.contains("This hook was set by husky-rs"),
+        "{}",
+        exported
+    );
+    assert!(exported.contains("This is a test hook"), "{}", exported);
+    let exported = fs::read_to_string(export_dir.join("commit-msg"))?;
+    assert!(exported.starts_with("#!"), "{}", exported);
+    assert!(!export_dir.join("pre-commit").exists());
+    let git_hooks_dir = project.path.join(".git").join("hooks");
+    assert!(!git_hooks_dir.join("pre-commit").exists());
+    assert!(!git_hooks_dir.join("commit-msg").exists());
+    fs::remove_dir_all(&export_dir)?;
+    Ok(())
+}
+
+// Test: Verify installing into the hooks' own source directory fails before touching them
+#[test]
+fn test_husky_rs_dest_dir_same_as_source() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-same-dir-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+
+    let output =
+        project.run_cargo_command_with_output("build", &[("HUSKY_DEST_DIR", ".husky/hooks")])?;
+    assert!(!output.status.success(), "Build unexpectedly succeeded");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("the same directory"), "{}", stderr);
+    let source = fs::read_to_string(project.path.join(".husky").join("hooks").join("pre-commit"))?;
+    assert_eq!(source, HOOK_TEMPLATE);
+    Ok(())
+}
+
+// Test: Verify editing a helper sourced from a subdirectory of `.husky` reruns the installation
+#[test]
+fn test_husky_rs_watches_helpers() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-watch-helpers-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.add_husky_metadata("report = true")?;
+    let lib_dir = project.path.join(".husky").join("lib");
+    fs::create_dir_all(&lib_dir)?;
+    fs::write(lib_dir.join("common.sh"), "check() { echo checking; }\n")?;
+    project.create_hook(
+        "pre-commit",
+        "#!/bin/sh\n. \"$(git rev-parse --show-toplevel)/.husky/lib/common.sh\"\ncheck\n",
+    )?;
+    // The second build settles the first one having written into `.git/hooks`
+    project.run_cargo_command("build")?;
+    project.run_cargo_command("build")?;
+
+    // The report is rewritten on every run, so its absence shows whether the script reran
+    let report = project.build_file_path(&Path::new("out").join("husky-report.txt"))?;
+    fs::remove_file(&report)?;
+    project.run_cargo_command("build")?;
+    assert!(!report.exists(), "Reran without any change");
+
+    // Modification times may be too coarse to tell the edit apart from the last build
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    fs::write(
+        lib_dir.join("common.sh"),
+        "check() { echo still checking; }\n",
+    )?;
+    project.run_cargo_command("build")?;
+    assert!(
+        report.exists(),
+        "Editing the helper didn't rerun the installation"
+    );
+    Ok(())
+}
+
+// Test: Verify hooks are installed from a .tar.gz archive
+#[cfg(feature = "archive")]
+#[test]
+fn test_husky_rs_with_hooks_archive() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-archive-test-")?;
+    project.add_husky_rs_with_features("dependencies", &["archive"])?;
+    project.add_husky_metadata("hooks_archive = \"hooks.tar.gz\"")?;
+
+    let archive = fs::File::create(project.path.join("hooks.tar.gz"))?;
+    let encoder = flate2::write::GzEncoder::new(archive, flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    for hook in HOOK_TYPES {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(HOOK_TEMPLATE.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(
+            &mut header,
+            format!("hooks/{}", hook),
+            HOOK_TEMPLATE.as_bytes(),
+        )?;
+    }
+    builder.into_inner()?.finish()?;
+
+    project.run_cargo_command("build")?;
+    project.verify_hooks(true)
+}
+
+// Test: Verify `HUSKY_SELFTEST` installs and runs a sample hook instead of the project's hooks
+#[test]
+fn test_husky_rs_self_test() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-self-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    // None of these may keep the sample hook from being installed
+    project.add_husky_metadata("skip_in_ci = true\nprofiles = [\"release\"]")?;
+    let envs = [
+        ("HUSKY_SELFTEST", "1"),
+        ("CI", "1"),
+        ("HUSKY_CHECK", "1"),
+        ("HUSKY_ONLY_MISSING", "1"),
+    ];
+    project.run_cargo_command_with_env("build", &envs)?;
+
+    let output = project.build_script_output()?;
+    assert!(output.contains("Self-test passed"), "{}", output);
+    project.verify_hooks(false)?;
+    Ok(())
+}
+
+// Test: Verify settings in `[workspace.metadata.husky]` apply to a member's build
+#[test]
+fn test_husky_rs_workspace_metadata() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-workspace-metadata-test-")?;
+    let member = project.path.join("member");
+    Command::new("cargo")
+        .args(["new", "--bin", "--vcs", "none", "member"])
+        .current_dir(&project.path)
+        .status()?;
+    fs::write(
+        project.path.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"member\"]\nresolver = \"2\"\n\n\
+         [workspace.metadata.husky]\nhooks_src = \"githooks\"\n",
+    )?;
+    let crate_path = get_relative_path(&member, Path::new(env!("CARGO_MANIFEST_DIR")));
+    let mut member_toml = fs::read_to_string(member.join("Cargo.toml"))?;
+    member_toml.push_str(&format!("husky-rs = {{ path = {:?} }}\n", crate_path));
+    fs::write(member.join("Cargo.toml"), member_toml)?;
+    let hooks_src = project.path.join("githooks");
+    fs::create_dir_all(&hooks_src)?;
+    fs::write(hooks_src.join("pre-commit"), HOOK_TEMPLATE)?;
+
+    project
+        .cargo_command(&["build", "-p", "member"], &[])
+        .status()?;
+    let hook = project.path.join(".git").join("hooks").join("pre-commit");
+    let installed = fs::read_to_string(&hook)?;
+    assert!(installed.contains("This is a test hook"), "{}", installed);
+    Ok(())
+}
+
+// Test: Verify a symlinked `.git/hooks` is reported with the directory it resolves to
+#[cfg(unix)]
+#[test]
+fn test_husky_rs_symlinked_hooks_dir() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-symlinked-hooks-dir-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    let hooks_dir = project.path.join(".git").join("hooks");
+    let shared = project.path.join("shared-hooks");
+    fs::create_dir_all(&shared)?;
+    if hooks_dir.exists() {
+        fs::remove_dir_all(&hooks_dir)?;
+    }
+    std::os::unix::fs::symlink(&shared, &hooks_dir)?;
+    project.run_cargo_command("build")?;
+
+    let output = project.build_script_output()?;
+    let resolved = fs::canonicalize(&shared)?;
+    assert!(
+        output.contains(&format!(
+            "is a symlink, hooks are installed into '{}'",
+            resolved.display()
+        )),
+        "{}",
+        output
+    );
+    assert!(shared.join("pre-commit").is_file());
+    Ok(())
+}
+
+// Test: Verify edits behind a symlinked `.husky` are picked up on the next build
+#[cfg(unix)]
+#[test]
+fn test_husky_rs_symlinked_husky_dir() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-symlinked-husky-dir-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    let shared = project.path.join("shared-husky");
+    fs::create_dir_all(shared.join("hooks"))?;
+    fs::write(shared.join("hooks").join("pre-commit"), HOOK_TEMPLATE)?;
+    std::os::unix::fs::symlink(&shared, project.path.join(".husky"))?;
+    project.run_cargo_command("build")?;
+
+    let hook = project.path.join(".git").join("hooks").join("pre-commit");
+    assert!(fs::read_to_string(&hook)?.contains("This is a test hook"));
+    fs::write(
+        shared.join("hooks").join("pre-commit"),
+        "#!/bin/sh\necho \"edited in the shared directory\"\n",
+    )?;
+    project.run_cargo_command("build")?;
+    let installed = fs::read_to_string(&hook)?;
+    assert!(
+        installed.contains("edited in the shared directory"),
+        "{}",
+        installed
+    );
+    Ok(())
+}
+
+// Test: Verify a rebuild after `cargo clean` reuses the cached bodies and rewrites no hook
+#[test]
+fn test_husky_rs_cargo_clean_rewrites_nothing() -> Result<(), Error> {
+    let project = TestProject::new("husky-rs-clean-rewrites-nothing-test-")?;
+    project.add_husky_rs_to_toml("dependencies")?;
+    project.create_hooks()?;
+    project.run_cargo_command("build")?;
+    let hooks_dir = project.path.join(".git").join("hooks");
+    let modified = || -> Result<Vec<SystemTime>, Error> {
+        HOOK_TYPES
+            .iter()
+            .map(|hook| fs::metadata(hooks_dir.join(hook))?.modified())
+            .collect()
+    };
+    let before = modified()?;
+
+    project.run_cargo_command("clean")?;
+    project.run_cargo_command_with_env("build", &[("HUSKY_LOG", "debug")])?;
+    let output = project.build_script_output()?;
+    assert!(
+        output.contains("Reusing the body of hook 'pre-commit'"),
+        "Sources read again after `cargo clean`: {}",
+        output
+    );
+    assert!(output.contains("Installed 0 hook(s)"), "{}", output);
+    assert_eq!(modified()?, before);
+    Ok(())
+}